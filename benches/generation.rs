@@ -0,0 +1,99 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use dungeon_generator::{generate, Config, Rectangle, Router, SpatialIndex, Strategy, Vector};
+use std::hint::black_box;
+
+const ROOMS: [usize; 5] = [10, 100, 500, 1000, 5000];
+/// The A* router searches around every room, bigger dungeons take seconds
+const ASTAR_ROOMS: [usize; 3] = [10, 100, 500];
+
+fn config(rooms: usize) -> Config {
+    // the default config, with as many rooms as asked
+    Config::builder().seed(42).rooms_count(rooms).build().unwrap()
+}
+
+fn bench_scatter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scatter");
+
+    group.sample_size(10);
+
+    for rooms in ROOMS {
+        group.bench_with_input(BenchmarkId::from_parameter(rooms), &config(rooms), |b, config| {
+            b.iter(|| generate(black_box(config.clone())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_astar(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scatter-astar");
+
+    group.sample_size(10);
+
+    for rooms in ASTAR_ROOMS {
+        let config = Config::builder()
+            .seed(42)
+            .rooms_count(rooms)
+            .router(Router::AStar)
+            .build()
+            .unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(rooms), &config, |b, config| {
+            b.iter(|| generate(black_box(config.clone())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_bsp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bsp");
+
+    group.sample_size(10);
+
+    for rooms in ROOMS {
+        // about one leaf of 20x20 cells per room
+        let side = (rooms as f64).sqrt().ceil() as u32 * 20 + 40;
+        let config = Config::builder()
+            .seed(42)
+            .rooms_count(rooms)
+            .strategy(Strategy::Bsp)
+            .bounds(side, side)
+            .build()
+            .unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(rooms), &config, |b, config| {
+            b.iter(|| generate(black_box(config.clone())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+/// Overlap queries on a grid of rooms, the cost must not grow with the
+/// number of rooms
+fn bench_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("index");
+
+    for rooms in ROOMS {
+        let columns = (rooms as f64).sqrt().ceil() as i32;
+        let mut index = SpatialIndex::default();
+
+        for n in 0..rooms as i32 {
+            let p1 = Vector { x: n % columns * 12, y: n / columns * 12 };
+
+            index.insert(Rectangle { p2: Vector { x: p1.x + 7, y: p1.y + 7 }, p1 });
+        }
+
+        let area = Rectangle { p1: Vector { x: 30, y: 30 }, p2: Vector { x: 45, y: 45 } };
+
+        group.bench_with_input(BenchmarkId::from_parameter(rooms), &index, |b, index| {
+            b.iter(|| index.query(black_box(&area)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_scatter, bench_astar, bench_bsp, bench_index);
+criterion_main!(benches);
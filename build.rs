@@ -0,0 +1,18 @@
+/// Generate the C header of the lib from its `#[repr(C)]` types and
+/// `extern "C"` functions (see `cbindgen.toml`) with `--features header`.
+/// The header is checked in, the other builds leave the sources untouched.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "header")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+
+        println!("cargo:rerun-if-changed=src");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        cbindgen::generate(&crate_dir)
+            .expect("Cannot generate the C header")
+            .write_to_file("include/dungeon_generator.h");
+    }
+}
@@ -1,35 +1,11 @@
+#include <inttypes.h>
 #include <stdio.h>
 #include <stdint.h>
 #include <stdlib.h>
 #include <stdbool.h>
+#include <string.h>
 
-typedef struct Handle Handle;
-
-typedef struct {
-	uint8_t x;
-	uint8_t y;
-} Vector;
-
-typedef struct {
-	uint8_t min;
-	uint8_t max;
-} MinMax;
-
-typedef struct {
-	uint32_t seed;
-	size_t rooms_count;
-	Vector rooms_min_size;
-	Vector rooms_max_size;
-	MinMax rooms_spacing;
-	MinMax path_extension;
-} Config;
-
-extern Handle* map_create(Config *config);
-extern void map_destroy(Handle *handle);
-extern Vector map_size(Handle *handle);
-extern char* map_as_string(Handle *handle);
-extern char* map_as_bytes(Handle *handle);
-extern Config* get_config();
+#include "dungeon_generator.h"
 
 void print_ascii(char* map)
 {
@@ -37,13 +13,13 @@ void print_ascii(char* map)
 	//printf("Generated map (ASCII): %s\n", map);
 }
 
-void print_bytes(char* map, Vector size)
+void print_bytes(unsigned char* map, Vector_u32 size)
 {
 	printf("Generated map (bytes):\n");
 
-	for (int y = 0; y < size.y; y++)
+	for (uint32_t y = 0; y < size.y; y++)
 	{
-		for (int x = 0; x < size.x; x++)
+		for (uint32_t x = 0; x < size.x; x++)
 		{
 			printf("%i", map[x + y * size.x]);
 		}
@@ -52,49 +28,222 @@ void print_bytes(char* map, Vector size)
 	}
 }
 
-Config* create_config(int seed)
+void print_layout(Handle* handle)
+{
+	size_t rooms = map_rooms_count(handle);
+	size_t connections = map_connections_count(handle);
+
+	for (size_t i = 0; i < rooms; i++)
+	{
+		RoomLayout room = map_room(handle, i);
+
+		printf("Room %zu: (%i, %i) -> (%i, %i), shape %i\n", room.id,
+			room.bounds.p1.x, room.bounds.p1.y, room.bounds.p2.x, room.bounds.p2.y, room.shape);
+	}
+
+	for (size_t i = 0; i < connections; i++)
+	{
+		ConnectionInfo connection = map_connection(handle, i);
+
+		printf("Connection %zu: room %zu -> room %zu:", connection.id, connection.from, connection.to);
+
+		for (size_t w = 0; w < connection.waypoints_count; w++)
+		{
+			Vector_i32 p = map_connection_waypoint(handle, i, w);
+
+			printf(" (%i, %i)", p.x, p.y);
+		}
+
+		printf("\n");
+	}
+}
+
+void print_stats(Handle* handle)
+{
+	StatsInfo stats = map_stats(handle);
+
+	printf("Stats: %zu rooms placed of %zu, %zu cycles, %zu dead ends, %.1f%% of floor\n",
+		stats.rooms, stats.requested_rooms, stats.cycles, stats.dead_ends, stats.floor_ratio * 100.0);
+	printf("Doors per room:");
+
+	for (size_t i = 0; i < stats.rooms; i++)
+	{
+		printf(" %zu", map_room_doors(handle, i));
+	}
+
+	printf("\n");
+}
+
+void print_distances(Handle* handle)
+{
+	uint32_t distance = 0;
+	size_t farthest = map_farthest_room(handle, 0, &distance);
+
+	if (farthest == SIZE_MAX)
+	{
+		return;
+	}
+
+	printf("Farthest room from room 0: room %zu, %u tiles away\n", farthest, distance);
+
+	// walking distances from the first door of the map, corridors cost twice as much
+	Vector_u32 size = map_size(handle);
+	size_t count = (size_t) size.x * size.y;
+	uint32_t* distances = malloc(count * sizeof(uint32_t));
+
+	if (distances == NULL || map_connections_count(handle) == 0)
+	{
+		free(distances);
+
+		return;
+	}
+
+	Vector_i32 door = map_connection(handle, 0).from_door;
+	TileCosts costs = { .floor = 1, .corridor = 2, .door = 1 };
+	size_t reached = 0;
+
+	if (map_copy_distances(handle, &door, 1, costs, distances, count) == count)
+	{
+		for (size_t i = 0; i < count; i++)
+		{
+			reached += distances[i] != DISTANCE_UNREACHABLE;
+		}
+	}
+
+	printf("Tiles reached from the door (%i, %i): %zu\n", door.x, door.y, reached);
+	free(distances);
+}
+
+Config* create_config(uint64_t seed)
 {
 	Config* config = get_config();
 
 	config->seed = seed;
 	config->rooms_count = 7;
-	config->rooms_min_size = (Vector) { 4, 4 };
-	config->rooms_max_size = (Vector) { 7, 7 };
+	config->rooms_min_size = (Vector_u32) { 4, 4 };
+	config->rooms_max_size = (Vector_u32) { 7, 7 };
 	config->rooms_spacing = (MinMax) { 3, 5 };
 	config->path_extension = (MinMax) { 2, 4 };
+	config->walls = NEIGHBOURHOOD_EIGHT;
+	config->router = ROUTER_ROTATION;
+	config->turn_penalty = 4;
+	config->strategy = STRATEGY_SCATTER;
+	config->bounds.x = 64;
+	config->bounds.y = 48;
+	config->cave.fill = 45;
+	config->cave.birth = 5;
+	config->cave.survival = 4;
+	config->cave.iterations = 4;
+	config->cave.min_pocket = 8;
+	config->cave.pockets = POCKETS_JOIN;
+	config->shapes.rectangle = 1;
+	config->shapes.ellipse = 1;
+	config->shapes.cross = 0;
+	config->shapes.l_shape = 0;
+	config->shapes.pillars = 0;
+	config->shapes.composite = 0;
+	config->neighbours = 3;
+	config->loop_factor = 15;
 
 	return config;
 }
 
-void print_map(Handle* handle, bool draw_ascii, bool draw_bytes)
+void print_map(Handle* handle, bool draw_ascii, bool draw_bytes, bool draw_json)
 {
 	if (draw_ascii)
 	{
-		print_ascii(map_as_string(handle));
+		char* ascii = map_as_string(handle);
+
+		print_ascii(ascii);
+		map_string_free(ascii);
 	}
 
 	if (draw_bytes)
 	{
-		print_bytes(map_as_bytes(handle), map_size(handle));
+		size_t length = 0;
+		unsigned char* bytes = map_as_bytes(handle, &length);
+		Vector_u32 size = map_size(handle);
+
+		if (length == (size_t) size.x * size.y)
+		{
+			print_bytes(bytes, size);
+		}
+
+		// or into a buffer of the caller, asking for the length first
+		size_t needed = map_copy_bytes(handle, NULL, 0);
+		unsigned char* copy = malloc(needed);
+
+		if (copy != NULL && map_copy_bytes(handle, copy, needed) == needed)
+		{
+			printf("Copied %zu bytes, %s\n", needed,
+				needed == length && memcmp(copy, bytes, length) == 0 ? "same tiles" : "different tiles");
+		}
+
+		free(copy);
+		map_bytes_free(bytes, length);
+	}
+
+	if (draw_json)
+	{
+		char* json = map_as_json(handle);
+
+		printf("%s\n", json);
+		map_string_free(json);
 	}
 
 	printf("\n");
 }
 
-void draw_map(uint32_t seed)
+void draw_map(Config* config)
 {
-	Config* config = create_config(seed);
+	// the config is consumed by map_create
+	uint64_t seed = config->seed;
 	Handle* handle = map_create(config);
 
-	printf("Map %u", seed);
-	print_map(handle, true, false);
+	if (handle == NULL)
+	{
+		printf("Map %" PRIu64 ": error %i: %s\n", seed, last_error_code(), last_error_message());
+
+		return;
+	}
+
+	printf("Map %" PRIu64, seed);
+	print_map(handle, true, true, true);
+	print_layout(handle);
+	print_stats(handle);
+	print_distances(handle);
 
 	map_destroy(handle);
 }
 
 int main()
 {
-	draw_map(42);
+	if (abi_version() != DUNGEON_GENERATOR_ABI_VERSION)
+	{
+		fprintf(stderr, "The lib has ABI version %u, the header %u\n", abi_version(), DUNGEON_GENERATOR_ABI_VERSION);
+
+		return 1;
+	}
+
+	// configs not passed to map_create are released by the caller
+	Config* defaults = get_config();
+
+	printf("Default config: %u neighbours, loop factor %u%%\n\n", defaults->neighbours, defaults->loop_factor);
+	config_free(defaults);
+
+	draw_map(create_config(42));
+
+	Config* preset = config_from_preset("tight-crypt");
+
+	if (preset == NULL)
+	{
+		printf("Preset tight-crypt: error %i: %s\n", last_error_code(), last_error_message());
+
+		return 1;
+	}
+
+	preset->seed = 7;
+	draw_map(preset);
 
 	return 0;
 }
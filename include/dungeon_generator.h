@@ -0,0 +1,514 @@
+#ifndef DUNGEON_GENERATOR_H
+#define DUNGEON_GENERATOR_H
+
+/* Generated by cbindgen from the Rust sources, do not edit */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Version of the C ABI, bumped on every incompatible change of the
+ * functions or the types shared with C
+ */
+#define DUNGEON_GENERATOR_ABI_VERSION 1
+
+/**
+ * Distance of the tiles which cannot be walked to in the grids of
+ * `map_copy_distances`
+ */
+#define DISTANCE_UNREACHABLE UINT32_MAX
+
+/**
+ * Neighbours taken into account when wrapping walkable tiles with walls,
+ * `Four` leaves the corners of the rooms and the corridor turns open.
+ */
+typedef enum Neighbourhood {
+  NEIGHBOURHOOD_FOUR = 4,
+  NEIGHBOURHOOD_EIGHT = 8,
+} Neighbourhood;
+
+/**
+ * What to do with the caverns left apart from the biggest one
+ */
+typedef enum Pockets {
+  /**
+   * Fill them back with rock
+   */
+  POCKETS_CULL,
+  /**
+   * Dig a corridor from each of them to the nearest connected cavern
+   */
+  POCKETS_JOIN,
+} Pockets;
+
+/**
+ * Kind of shape of a room, within its bounding rectangle
+ */
+typedef enum RoomShape {
+  ROOM_SHAPE_RECTANGLE,
+  ROOM_SHAPE_ELLIPSE,
+  /**
+   * Two arms crossing in the middle of the bounds
+   */
+  ROOM_SHAPE_CROSS,
+  /**
+   * L-shaped, a rectangle with one corner cut off
+   */
+  ROOM_SHAPE_L,
+  /**
+   * Rectangle with four pillars, two cells away from the corners
+   */
+  ROOM_SHAPE_PILLARS,
+  /**
+   * Two or three overlapping rectangles
+   */
+  ROOM_SHAPE_COMPOSITE,
+  /**
+   * Hand-authored template
+   */
+  ROOM_SHAPE_PREFAB,
+} RoomShape;
+
+/**
+ * Corridor solver selected in the `Config`
+ */
+typedef enum Router {
+  /**
+   * Extend the corridor from one room and turn toward the other one
+   */
+  ROUTER_ROTATION,
+  /**
+   * A* search on the even cells of the grid, around the rooms
+   */
+  ROUTER_A_STAR,
+} Router;
+
+/**
+ * Algorithm placing the rooms, selected in the `Config`
+ */
+typedef enum Strategy {
+  /**
+   * Attach each room next to a random existing room
+   */
+  STRATEGY_SCATTER,
+  /**
+   * Split the `bounds` of the config recursively, one room per leaf
+   */
+  STRATEGY_BSP,
+  /**
+   * Carve caverns in the `bounds` of the config with a cellular automaton,
+   * following the `cave` rules of the config
+   */
+  STRATEGY_CAVE,
+} Strategy;
+
+/**
+ * Opaque to C, created by `map_create` and released by `map_destroy`
+ */
+typedef struct Handle Handle;
+
+typedef struct Vector_u32 {
+  uint32_t x;
+  uint32_t y;
+} Vector_u32;
+
+typedef struct MinMax {
+  uint32_t min;
+  uint32_t max;
+} MinMax;
+
+/**
+ * Rules of the cellular automaton carving the caves
+ */
+typedef struct CaveRules {
+  /**
+   * Percentage of the cells filled with rock before the first iteration
+   */
+  uint32_t fill;
+  /**
+   * An open cell turns into rock when at least `birth` of its 8
+   * neighbours are rock
+   */
+  uint32_t birth;
+  /**
+   * A rock cell stays rock when at least `survival` of its 8 neighbours
+   * are rock
+   */
+  uint32_t survival;
+  uint32_t iterations;
+  /**
+   * Caverns with fewer cells are always filled back with rock
+   */
+  uint32_t min_pocket;
+  /**
+   * Code of a `Pockets`
+   */
+  uint32_t pockets;
+} CaveRules;
+
+/**
+ * Relative weights of the room shapes, a shape with a weight of 0 is never
+ * picked
+ */
+typedef struct ShapeWeights {
+  uint32_t rectangle;
+  uint32_t ellipse;
+  uint32_t cross;
+  uint32_t l_shape;
+  uint32_t pillars;
+  uint32_t composite;
+} ShapeWeights;
+
+/**
+ * Settings of the generation. Serialised fields use the names of the
+ * struct, the missing ones keep their `Config::default` value when loading a
+ * file.
+ *
+ * C writes the enums as `u32` codes, checked by `validate` and read back
+ * with the getters of the same name.
+ */
+typedef struct Config {
+  uint64_t seed;
+  size_t rooms_count;
+  struct Vector_u32 rooms_min_size;
+  struct Vector_u32 rooms_max_size;
+  struct MinMax rooms_spacing;
+  struct MinMax path_extension;
+  /**
+   * Code of a `Neighbourhood`
+   */
+  uint32_t walls;
+  /**
+   * Code of a `Router`
+   */
+  uint32_t router;
+  uint32_t turn_penalty;
+  /**
+   * Code of a `Strategy`
+   */
+  uint32_t strategy;
+  /**
+   * Area covered by the `Bsp` & `Cave` strategies, ignored by `Scatter`
+   */
+  struct Vector_u32 bounds;
+  /**
+   * Used by the `Cave` strategy only
+   */
+  struct CaveRules cave;
+  /**
+   * Chances of each room shape, ignored by the `Cave` strategy
+   */
+  struct ShapeWeights shapes;
+  /**
+   * Number of nearest rooms considered for the extra connections of the
+   * `Scatter` strategy
+   */
+  uint32_t neighbours;
+  /**
+   * Percentage of these extra connections added to the spanning tree of
+   * the rooms, making loops
+   */
+  uint32_t loop_factor;
+} Config;
+
+typedef struct Vector_i32 {
+  int32_t x;
+  int32_t y;
+} Vector_i32;
+
+typedef struct Rectangle {
+  struct Vector_i32 p1;
+  struct Vector_i32 p2;
+} Rectangle;
+
+typedef struct RoomLayout {
+  size_t id;
+  struct Rectangle bounds;
+  enum RoomShape shape;
+} RoomLayout;
+
+/**
+ * C view of a `ConnectionLayout`, waypoints are fetched one by one with
+ * `map_connection_waypoint`
+ */
+typedef struct ConnectionInfo {
+  size_t id;
+  size_t from;
+  size_t to;
+  struct Vector_i32 from_door;
+  struct Vector_i32 to_door;
+  size_t waypoints_count;
+} ConnectionInfo;
+
+/**
+ * C view of a `DungeonStats`, the doors of each room are fetched one by one
+ * with `map_room_doors`
+ */
+typedef struct StatsInfo {
+  uint32_t width;
+  uint32_t height;
+  size_t rooms;
+  size_t requested_rooms;
+  size_t connections;
+  size_t repairs;
+  size_t floor_tiles;
+  size_t corridor_tiles;
+  size_t door_tiles;
+  double floor_ratio;
+  double corridor_ratio;
+  double empty_ratio;
+  double utilisation;
+  size_t dead_ends;
+  size_t leaf_rooms;
+  size_t cycles;
+  double average_corridor_length;
+  size_t max_corridor_length;
+  double average_room_distance;
+  size_t max_room_distance;
+  size_t corridor_overlaps;
+} StatsInfo;
+
+/**
+ * Cost of a step onto each kind of walkable tile, the other tiles cannot be
+ * walked on
+ */
+typedef struct TileCosts {
+  uint32_t floor;
+  uint32_t corridor;
+  uint32_t door;
+} TileCosts;
+
+/**
+ * Version of the C ABI of the linked lib, to be compared with the
+ * `DUNGEON_GENERATOR_ABI_VERSION` of the header the program was built with
+ */
+uint32_t abi_version(void);
+
+/**
+ * Config with empty sizes & ranges (see `Config::new`), to be filled then
+ * passed to `map_create` or released with `config_free`.
+ */
+struct Config *get_config(void);
+
+/**
+ * Config of a built-in preset (see `Config::preset`), to be changed then
+ * passed to `map_create` or released with `config_free`. Returns NULL if
+ * `name` is not a preset, see `last_error_code` and `last_error_message`.
+ *
+ * # Safety
+ *
+ * `name` must be either null or a valid NUL terminated string.
+ */
+struct Config *config_from_preset(const char *name);
+
+/**
+ * Release a config which has not been passed to `map_create`.
+ *
+ * # Safety
+ *
+ * `config` must be either null or a pointer obtained from `get_config`, it
+ * must not be used afterward.
+ */
+void config_free(struct Config *config);
+
+/**
+ * Returns NULL if the generation fails, see `last_error_code` and
+ * `last_error_message` to know why.
+ *
+ * # Safety
+ *
+ * `config` must be a pointer obtained from `get_config`, it is consumed by
+ * this function and must not be used afterward.
+ */
+struct Handle *map_create(struct Config *config);
+
+/**
+ * Code of the error which made the last `map_create` or
+ * `config_from_preset` call of the thread fail, 0 if it succeeded
+ */
+int last_error_code(void);
+
+/**
+ * Message of the error which made the last `map_create` or
+ * `config_from_preset` call of the thread fail, NULL if it succeeded. The
+ * string is owned by the library and stays valid until the next call to
+ * either function on the same thread.
+ */
+const char *last_error_message(void);
+
+/**
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+int map_destroy(struct Handle *handle);
+
+/**
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+struct Vector_u32 map_size(struct Handle *handle);
+
+/**
+ * ASCII grid of the map, NULL if `handle` is NULL. The string is owned by
+ * the caller and must be released with `map_string_free`.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+char *map_as_string(struct Handle *handle);
+
+/**
+ * JSON document with the map, its layout and the config it was generated
+ * with, see `to_json`. NULL if `handle` is NULL. The string is owned by the
+ * caller and must be released with `map_string_free`.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+char *map_as_json(struct Handle *handle);
+
+/**
+ * Release a string returned by `map_as_string` or `map_as_json`.
+ *
+ * # Safety
+ *
+ * `string` must be either null or a pointer returned by `map_as_string` or
+ * `map_as_json`, it must not be used afterward.
+ */
+void map_string_free(char *string);
+
+/**
+ * Tiles of the map, one byte per tile, row after row. The number of bytes
+ * is written to `length`. NULL, with a length of 0, if `handle` is NULL.
+ * The bytes are owned by the caller and must be released with
+ * `map_bytes_free`.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`,
+ * `length` must be either null or a valid pointer.
+ */
+unsigned char *map_as_bytes(struct Handle *handle, size_t *length);
+
+/**
+ * Copy the tiles of the map, one byte per tile, row after row, into a
+ * buffer of `size` bytes owned by the caller. Returns the number of bytes of
+ * the map, nothing is copied if it is bigger than `size`. Returns 0 if
+ * `handle` is NULL.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`,
+ * `buffer` must be either null or valid for writes of `size` bytes.
+ */
+size_t map_copy_bytes(struct Handle *handle, unsigned char *buffer, size_t size);
+
+/**
+ * Release the bytes returned by `map_as_bytes`.
+ *
+ * # Safety
+ *
+ * `bytes` must be either null or a pointer returned by `map_as_bytes`, with
+ * the `length` it returned, it must not be used afterward.
+ */
+void map_bytes_free(unsigned char *bytes, size_t length);
+
+/**
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+size_t map_rooms_count(struct Handle *handle);
+
+/**
+ * Returns a room with an id of `usize::MAX` if `index` is out of bounds.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+struct RoomLayout map_room(struct Handle *handle, size_t index);
+
+/**
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+size_t map_connections_count(struct Handle *handle);
+
+/**
+ * Returns a connection with an id of `usize::MAX` if `index` is out of bounds.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+struct ConnectionInfo map_connection(struct Handle *handle, size_t index);
+
+/**
+ * Returns (0, 0) if either `index` or `waypoint` is out of bounds.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+struct Vector_i32 map_connection_waypoint(struct Handle *handle, size_t index, size_t waypoint);
+
+/**
+ * Quality figures of the map, all zero if `handle` is NULL.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+struct StatsInfo map_stats(struct Handle *handle);
+
+/**
+ * Doors of the room at `index`, as listed by `map_room`. Returns 0 if
+ * `index` is out of bounds.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`.
+ */
+size_t map_room_doors(struct Handle *handle, size_t index);
+
+/**
+ * Copy the walking distance from the nearest of the `sources` (`count`
+ * positions, in map coordinates) to each tile of the map, row after row,
+ * into a buffer of `size` values owned by the caller. Tiles which cannot be
+ * walked to are `DISTANCE_UNREACHABLE`. Returns the number of tiles of the
+ * map, nothing is copied if it is bigger than `size`. Returns 0 if `handle`
+ * is NULL.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`,
+ * `sources` must be either null or valid for reads of `count` positions and
+ * `buffer` either null or valid for writes of `size` values.
+ */
+size_t map_copy_distances(struct Handle *handle,
+                          const struct Vector_i32 *sources,
+                          size_t count,
+                          struct TileCosts costs,
+                          uint32_t *buffer,
+                          size_t size);
+
+/**
+ * Id of the room the farthest from the room `start` (an id), walking one
+ * tile at a time, and its distance in `distance` if not NULL. Returns
+ * `usize::MAX` if there is no room `start` or if `handle` is NULL.
+ *
+ * # Safety
+ *
+ * `handle` must be either null or a pointer returned by `map_create`,
+ * `distance` must be either null or valid for writes.
+ */
+size_t map_farthest_room(struct Handle *handle, size_t start, uint32_t *distance);
+
+#endif  /* DUNGEON_GENERATOR_H */
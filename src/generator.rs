@@ -1,91 +1,60 @@
+pub mod analysis;
+pub mod distance;
+pub mod errors;
+pub mod export;
+pub mod layout;
 pub mod map;
 pub mod math;
-
+pub mod prefab;
+pub mod presets;
+pub mod router;
+pub mod shape;
+pub mod spatial;
+pub mod strategy;
+
+mod connectivity;
 mod dungeon;
-mod errors;
+mod planner;
+mod random;
 
+use crate::Config;
 use dungeon::Dungeon;
+use errors::GenerationError;
+use layout::DungeonLayout;
 use map::Map;
-use math::{Rectangle, Vector};
-use rand::{Rng, SeedableRng};
-use rand::distributions::{WeightedIndex, Distribution};
+use prefab::Prefab;
+use router::CorridorRouter;
+use spatial::SpatialIndex;
+use strategy::Strategy;
+use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
+use std::sync::Arc;
 
-pub fn run(seed: u64,
-           rooms: usize,
-           min: Vector<u8>,
-           max: Vector<u8>,
-           spacing: (u8, u8),
-           extension: (u8, u8),
-        ) -> Map {
-    let mut dungeon = Dungeon {
-        rooms: Vec::new(),
-        min_size: min,
-        max_size: max,
-        rooms_spacing: (spacing.0, spacing.1),
-        path_extension: (extension.0, extension.1),
-        rng: ChaCha8Rng::seed_from_u64(seed),
-    };
+pub fn run(config: &Config, mut router: Box<dyn CorridorRouter>, prefabs: &[Prefab]) -> Result<(Map, DungeonLayout), GenerationError> {
+    config.check()?;
 
-    if dungeon.max_size.x > 127 || dungeon.max_size.y > 127 {
-        panic!("Room size must be in the range [0,128)")
+    if config.strategy()? == Strategy::Cave {
+        return strategy::cave::generate(config);
     }
 
-    let mut rng = dungeon.rng.clone();
-    let choices = [1, 2, 3, 4];
-    let weights = [100, 5, 2, 1];
-    let dist = WeightedIndex::new(&weights).unwrap();
-
-    for i in 0..rooms {
-        add_room(&mut dungeon, i);
-
-        if i < 1 {
-            continue;
-        }
-
-        let choice = choices[dist.sample(&mut rng)];
-        let connections = rng.gen_range(1..=i.min(choice));
-        let other_ids = (0..connections).map(|_| rng.gen_range(0..i));
-
-        for other_id in other_ids {
-            dungeon.connect_rooms(i, other_id);
-        }
-    }
-
-    dungeon.make_paths();
-    dungeon.to_map()
-}
-
-fn add_room(dungeon: &mut Dungeon, id: usize) {
-    let signed_size = Vector {
-        x: dungeon
-            .rng
-            .gen_range(dungeon.min_size.x..=dungeon.max_size.x) as i8,
-        y: dungeon
-            .rng
-            .gen_range(dungeon.min_size.y..=dungeon.max_size.y) as i8,
+    let mut dungeon = Dungeon {
+        rooms: Vec::new(),
+        connections: Vec::new(),
+        index: SpatialIndex::default(),
+        min_size: config.rooms_min_size.clone(),
+        max_size: config.rooms_max_size.clone(),
+        rooms_spacing: (config.rooms_spacing.min, config.rooms_spacing.max),
+        path_extension: (config.path_extension.min, config.path_extension.max),
+        shapes: config.shapes,
+        prefabs: prefabs.iter().cloned().map(Arc::new).collect(),
+        rng: ChaCha8Rng::seed_from_u64(config.seed),
     };
 
-    let rect = if id == 0 {
-        let mut p2 = signed_size;
-        // align the point to odd cells on grid
-        p2.x = if p2.x % 2 == 0 { p2.x + 1 } else { p2.x };
-        p2.y = if p2.y % 2 == 0 { p2.y + 1 } else { p2.y };
+    strategy::place_rooms(&mut dungeon, config)?;
 
-        Rectangle {
-            p1: Vector { x: 0, y: 0 },
-            p2,
-        }
-    } else {
-        match dungeon.find_empty_space(signed_size) {
-            Ok(rect) => rect,
-            Err(_error) => Rectangle {
-                p1: Vector { x: 0, y: 0 },
-                p2: Vector { x: 0, y: 0 },
-            }, // We don't care, the room will be discarded
-               //Err(error) => panic!("Cannot construct dungeon: {:?}", error),
-        }
-    };
+    let unsolved = dungeon.make_paths(router.as_mut());
+    let (map, repairs) = connectivity::repair(&mut dungeon, router.as_mut(), unsolved, config.walls()?)?;
+    let layout = DungeonLayout { repairs, ..dungeon.to_layout(map.offset()) };
 
-    dungeon.add_room(id, rect);
+    Ok((map, layout))
 }
@@ -0,0 +1,263 @@
+use crate::Config;
+use super::layout::DungeonLayout;
+use super::map::Map;
+use super::math::{Rectangle, Vector};
+use super::spatial::SpatialIndex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// bytes of the tiles, as listed by `Map::legend`
+const EMPTY: u8 = 0;
+const FLOOR: u8 = 1;
+const CORRIDOR: u8 = 2;
+const DOOR: u8 = 3;
+
+/// Figures of a generated dungeon, to judge its quality, compare seeds or
+/// filter them
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DungeonStats {
+    pub width: u32,
+    pub height: u32,
+    /// Rooms placed in the dungeon
+    pub rooms: usize,
+    /// Rooms asked by the config, which the `Cave` strategy ignores
+    pub requested_rooms: usize,
+    pub connections: usize,
+    /// Changes made to the connections to keep every room reachable
+    pub repairs: usize,
+    pub floor_tiles: usize,
+    pub corridor_tiles: usize,
+    pub door_tiles: usize,
+    /// Share of the tiles of the map which are floor, from 0 to 1
+    pub floor_ratio: f64,
+    pub corridor_ratio: f64,
+    pub empty_ratio: f64,
+    /// Share of the bounding box of the dungeon covered by something else
+    /// than empty tiles
+    pub utilisation: f64,
+    /// Corridor tiles leading nowhere, with a single walkable neighbour
+    pub dead_ends: usize,
+    /// Rooms with a single connection
+    pub leaf_rooms: usize,
+    /// Independent cycles of the room graph, `connections - rooms + parts`
+    /// where the parts are the groups of rooms linked together
+    pub cycles: usize,
+    /// Tiles walked along the corridor of each connection, from door to door
+    pub average_corridor_length: f64,
+    pub max_corridor_length: usize,
+    /// Connections crossed on the shortest way between two rooms, for every
+    /// pair of linked rooms
+    pub average_room_distance: f64,
+    pub max_room_distance: usize,
+    /// Rooms whose bounds are crossed by the corridor of a connection
+    /// between two other rooms, counted once per connection
+    pub corridor_overlaps: usize,
+    /// Doors of each room, in the order of `DungeonLayout::rooms`
+    pub doors_per_room: Vec<usize>,
+}
+
+impl DungeonStats {
+    pub fn new(map: &Map, layout: &DungeonLayout, config: &Config) -> DungeonStats {
+        let bytes = map.to_bytes();
+        let area = bytes.len();
+        let count = |byte: u8| bytes.iter().filter(|b| **b == byte).count();
+        let ratio = |n: usize| if area == 0 { 0.0 } else { n as f64 / area as f64 };
+        let (floor, corridor, door, empty) = (count(FLOOR), count(CORRIDOR), count(DOOR), count(EMPTY));
+        let graph = Graph::new(layout);
+        let corridors: Vec<usize> = layout.connections.iter().map(|c| corridor_length(&c.waypoints)).collect();
+        let (average_room_distance, max_room_distance) = graph.distances();
+
+        DungeonStats {
+            width: map.width,
+            height: map.height,
+            rooms: layout.rooms.len(),
+            requested_rooms: config.rooms_count,
+            connections: layout.connections.len(),
+            repairs: layout.repairs.len(),
+            floor_tiles: floor,
+            corridor_tiles: corridor,
+            door_tiles: door,
+            floor_ratio: ratio(floor),
+            corridor_ratio: ratio(corridor),
+            empty_ratio: ratio(empty),
+            utilisation: ratio(area - empty),
+            dead_ends: dead_ends(&bytes, map.width as usize, map.height as usize),
+            leaf_rooms: graph.edges.iter().filter(|e| e.len() == 1).count(),
+            cycles: (layout.connections.len() + graph.parts()).saturating_sub(layout.rooms.len()),
+            average_corridor_length: average(corridors.iter().sum::<usize>(), corridors.len()),
+            max_corridor_length: corridors.iter().copied().max().unwrap_or(0),
+            average_room_distance,
+            max_room_distance,
+            corridor_overlaps: corridor_overlaps(layout, &graph.indices),
+            doors_per_room: doors_per_room(layout, &graph.indices),
+        }
+    }
+}
+
+fn average(total: usize, count: usize) -> f64 {
+    if count == 0 { 0.0 } else { total as f64 / count as f64 }
+}
+
+/// Rooms as nodes, connections as edges, by position in the layout
+struct Graph {
+    /// Position of each room id in `DungeonLayout::rooms`
+    indices: HashMap<usize, usize>,
+    /// Rooms linked to each room, a room linked twice is listed twice
+    edges: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn new(layout: &DungeonLayout) -> Graph {
+        let indices: HashMap<usize, usize> = layout.rooms.iter().enumerate().map(|(i, r)| (r.id, i)).collect();
+        let mut edges = vec![Vec::new(); layout.rooms.len()];
+
+        for connection in layout.connections.iter() {
+            if let (Some(from), Some(to)) = (indices.get(&connection.from), indices.get(&connection.to)) {
+                edges[*from].push(*to);
+                edges[*to].push(*from);
+            }
+        }
+
+        Graph { indices, edges }
+    }
+
+    /// Connections crossed from a room to every other room, `None` for the
+    /// rooms out of reach
+    fn hops(&self, start: usize) -> Vec<Option<usize>> {
+        let mut hops = vec![None; self.edges.len()];
+        let mut queue = VecDeque::from([start]);
+
+        hops[start] = Some(0);
+
+        while let Some(room) = queue.pop_front() {
+            let next = hops[room].map(|h| h + 1);
+
+            for other in self.edges[room].iter() {
+                if hops[*other].is_none() {
+                    hops[*other] = next;
+                    queue.push_back(*other);
+                }
+            }
+        }
+
+        hops
+    }
+
+    /// Number of groups of rooms linked together
+    fn parts(&self) -> usize {
+        let mut seen = vec![false; self.edges.len()];
+        let mut parts = 0;
+
+        for room in 0..self.edges.len() {
+            if seen[room] {
+                continue;
+            }
+
+            parts += 1;
+
+            for (other, hops) in self.hops(room).into_iter().enumerate() {
+                seen[other] |= hops.is_some();
+            }
+        }
+
+        parts
+    }
+
+    /// Average & maximum connections crossed between two linked rooms
+    fn distances(&self) -> (f64, usize) {
+        let (mut total, mut pairs, mut max) = (0, 0, 0);
+
+        for room in 0..self.edges.len() {
+            for hops in self.hops(room).into_iter().skip(room + 1).flatten() {
+                total += hops;
+                pairs += 1;
+                max = max.max(hops);
+            }
+        }
+
+        (average(total, pairs), max)
+    }
+}
+
+/// Tiles from the first waypoint to the last one, both included. Two
+/// consecutive waypoints are aligned on one axis.
+fn corridor_length(waypoints: &[Vector<i32>]) -> usize {
+    let steps: i32 = waypoints.windows(2)
+        .map(|w| (w[1].x - w[0].x).abs() + (w[1].y - w[0].y).abs())
+        .sum();
+
+    if waypoints.is_empty() { 0 } else { steps as usize + 1 }
+}
+
+fn dead_ends(bytes: &[u8], width: usize, height: usize) -> usize {
+    let walkable = |n: usize| matches!(bytes[n], FLOOR | CORRIDOR | DOOR);
+
+    (0..bytes.len())
+        .filter(|n| bytes[*n] == CORRIDOR)
+        .filter(|n| {
+            let (x, y) = (n % width, n / width);
+            let neighbours = [
+                (x > 0).then(|| n - 1),
+                (x + 1 < width).then(|| n + 1),
+                (y > 0).then(|| n - width),
+                (y + 1 < height).then(|| n + width),
+            ];
+
+            neighbours.into_iter().flatten().filter(|m| walkable(*m)).count() <= 1
+        })
+        .count()
+}
+
+fn corridor_overlaps(layout: &DungeonLayout, indices: &HashMap<usize, usize>) -> usize {
+    let mut index = SpatialIndex::default();
+
+    for room in layout.rooms.iter() {
+        index.insert(room.bounds.clone());
+    }
+
+    layout.connections.iter()
+        .map(|connection| {
+            let ends = [indices.get(&connection.from), indices.get(&connection.to)];
+            let mut crossed = HashSet::new();
+
+            for w in connection.waypoints.windows(2) {
+                let (dx, dy) = ((w[1].x - w[0].x).signum(), (w[1].y - w[0].y).signum());
+                let mut p = w[0].clone();
+
+                loop {
+                    let cell = Rectangle { p1: p.clone(), p2: Vector { x: p.x + 1, y: p.y + 1 } };
+
+                    crossed.extend(index.query(&cell).into_iter().filter(|i| !ends.contains(&Some(i))));
+
+                    if p == w[1] {
+                        break;
+                    }
+
+                    // along x first, should the waypoints not be aligned
+                    if p.x != w[1].x {
+                        p.x += dx;
+                    } else {
+                        p.y += dy;
+                    }
+                }
+            }
+
+            crossed.len()
+        })
+        .sum()
+}
+
+/// Distinct doors of the connections of each room
+fn doors_per_room(layout: &DungeonLayout, indices: &HashMap<usize, usize>) -> Vec<usize> {
+    let mut doors = vec![HashSet::new(); layout.rooms.len()];
+
+    for connection in layout.connections.iter() {
+        for (room, door) in [(connection.from, &connection.from_door), (connection.to, &connection.to_door)] {
+            if let Some(i) = indices.get(&room) {
+                doors[*i].insert(door.clone());
+            }
+        }
+    }
+
+    doors.into_iter().map(|d| d.len()).collect()
+}
@@ -0,0 +1,252 @@
+use super::dungeon::{ConnectionId, Dungeon, Room, RoomId};
+use super::errors::GenerationError;
+use super::layout::Repair;
+use super::map::{Map, Neighbourhood};
+use super::math::Vector;
+use super::router::CorridorRouter;
+use std::collections::HashSet;
+
+/// Make sure every room can be walked to from the first one.
+///
+/// Connections whose corridor could not be traced are traced again or removed.
+/// Then every walkable tile is flooded from the first room, each tile out of
+/// reach is mapped back to its room or corridor: isolated rooms get connected
+/// to the nearest reachable room and stray corridors are removed, until the
+/// whole dungeon is connected. Returns the final map and the list of the
+/// changes made to the connections.
+pub fn repair(dungeon: &mut Dungeon, router: &mut dyn CorridorRouter, unsolved: Vec<(RoomId, RoomId)>, walls: Neighbourhood) -> Result<(Map, Vec<Repair>), GenerationError> {
+    let mut repairs = Vec::new();
+
+    for (from, to) in unsolved {
+        if dungeon.make_path(from, to, router).is_ok() {
+            repairs.push(Repair::Rerouted { from: from.0, to: to.0 });
+        } else {
+            dungeon.disconnect_rooms(from, to);
+            repairs.push(Repair::Removed { from: from.0, to: to.0 });
+        }
+    }
+
+    loop {
+        let mut map = dungeon.to_map()?;
+
+        map.add_walls(walls);
+
+        let cut = cut_parts(dungeon, &map);
+
+        if cut.rooms.is_empty() && cut.connections.is_empty() {
+            return Ok((map, repairs));
+        }
+
+        // corridors leading nowhere, both their rooms are reached another way
+        if !cut.connections.is_empty() {
+            for id in cut.connections {
+                if let Some(connection) = dungeon.connection(id) {
+                    let (from, to) = (connection.from, connection.to);
+
+                    dungeon.disconnect_rooms(from, to);
+                    repairs.push(Repair::Removed { from: from.0, to: to.0 });
+                }
+            }
+
+            continue;
+        }
+
+        // the first isolated room may only be reachable through the others,
+        // try them all before giving up
+        let joined = cut.rooms.iter().copied()
+            .find_map(|room| Some((room, join_reachable(dungeon, router, room, &cut.rooms)?)));
+
+        match joined {
+            Some((room, other)) => repairs.push(Repair::Added { from: room.0, to: other.0 }),
+            None => {
+                let room = cut.rooms[0];
+                let to = nearest_reachable(dungeon, room, &cut.rooms).first().map_or(0, |id| id.0);
+
+                return Err(GenerationError::PathUnsolvable { from: room.0, to });
+            }
+        }
+    }
+}
+
+/// Connect the room to the nearest room out of `isolated` a corridor can be
+/// traced to, returns the room joined
+fn join_reachable(dungeon: &mut Dungeon, router: &mut dyn CorridorRouter, room: RoomId, isolated: &[RoomId]) -> Option<RoomId> {
+    for other in nearest_reachable(dungeon, room, isolated) {
+        if !dungeon.connect_rooms(room, other) {
+            continue;
+        }
+
+        if dungeon.make_path(room, other, router).is_ok() {
+            return Some(other);
+        }
+
+        dungeon.disconnect_rooms(room, other);
+    }
+
+    None
+}
+
+/// Rooms out of `isolated`, the nearest to `room` first
+fn nearest_reachable(dungeon: &Dungeon, room: RoomId, isolated: &[RoomId]) -> Vec<RoomId> {
+    let center = dungeon.rooms[room.0].rect.center();
+    let mut candidates: Vec<RoomId> = dungeon.rooms.iter()
+        .map(|r| r.id)
+        .filter(|id| !isolated.contains(id))
+        .collect();
+
+    candidates.sort_by_key(|id| {
+        let other = dungeon.rooms[id.0].rect.center();
+
+        (other.x - center.x).pow(2) + (other.y - center.y).pow(2)
+    });
+
+    candidates
+}
+
+/// Parts of the dungeon holding walkable tiles which cannot be walked to from
+/// the first room
+#[derive(Debug, Default, PartialEq)]
+struct Cut {
+    /// Rooms with a tile out of reach, or a corridor out of reach
+    rooms: Vec<RoomId>,
+    /// Connections out of reach whose rooms are both reachable
+    connections: Vec<ConnectionId>,
+}
+
+/// Map every tile out of reach back to the room it lies in, or else to the
+/// connection whose corridor runs through it
+fn cut_parts(dungeon: &Dungeon, map: &Map) -> Cut {
+    let start = match dungeon.rooms.first() {
+        Some(room) => floor_cell(room) + map.offset().clone(),
+        None => return Cut::default(),
+    };
+    let mut rooms = HashSet::new();
+    let mut connections = HashSet::new();
+
+    for tile in map.unreachable_tiles(&start) {
+        // back to dungeon coordinates
+        let p = Vector { x: tile.x - map.offset().x, y: tile.y - map.offset().y };
+
+        if let Some(room) = dungeon.rooms.iter().find(|room| room.rect.is_inside(p.clone())) {
+            rooms.insert(room.id);
+            continue;
+        }
+
+        for (id, connection) in dungeon.connections.iter().enumerate() {
+            if connection.as_ref().is_some_and(|c| on_path(c.path.waypoints.as_slice(), &p)) {
+                connections.insert(ConnectionId(id));
+            }
+        }
+    }
+
+    // a corridor out of reach joining an isolated room is fixed with the room
+    for id in connections.iter().copied().collect::<Vec<_>>() {
+        if let Some(connection) = dungeon.connection(id) {
+            if rooms.contains(&connection.from) || rooms.contains(&connection.to) {
+                connections.remove(&id);
+            }
+        }
+    }
+
+    let mut rooms: Vec<RoomId> = rooms.into_iter().collect();
+    let mut connections: Vec<ConnectionId> = connections.into_iter().collect();
+
+    rooms.sort();
+    connections.sort();
+
+    Cut { rooms, connections }
+}
+
+/// Whether the point lies on the straight corridors between the waypoints
+fn on_path(waypoints: &[Vector<i32>], p: &Vector<i32>) -> bool {
+    let between = |a: i32, b: i32, v: i32| a.min(b) <= v && v <= a.max(b);
+
+    match waypoints {
+        [single] => single == p,
+        _ => waypoints.windows(2).any(|w| between(w[0].x, w[1].x, p.x) && between(w[0].y, w[1].y, p.y)),
+    }
+}
+
+/// First floor tile of a room, in dungeon coordinates
+fn floor_cell(room: &Room) -> Vector<i32> {
+    room.shape.cells(&room.rect)
+        .find(|p| room.shape.is_floor(&room.rect, p))
+        .unwrap_or_else(|| room.rect.p1.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::math::Rectangle;
+    use crate::generator::prefab::Prefab;
+    use crate::generator::shape::{Shape, ShapeWeights};
+    use crate::generator::spatial::SpatialIndex;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::sync::Arc;
+
+    fn dungeon(rooms: Vec<(Rectangle, Shape)>) -> Dungeon {
+        let mut dungeon = Dungeon {
+            min_size: Vector { x: 4, y: 4 },
+            max_size: Vector { x: 8, y: 8 },
+            rooms_spacing: (2, 4),
+            path_extension: (2, 4),
+            shapes: ShapeWeights::default(),
+            prefabs: Vec::new(),
+            rooms: Vec::new(),
+            connections: Vec::new(),
+            index: SpatialIndex::default(),
+            rng: ChaCha8Rng::seed_from_u64(0),
+        };
+
+        for (rect, shape) in rooms {
+            dungeon.add_room(rect, shape);
+        }
+
+        dungeon
+    }
+
+    fn map(dungeon: &Dungeon) -> Map {
+        let mut map = dungeon.to_map().unwrap();
+
+        map.add_walls(Neighbourhood::Eight);
+        map
+    }
+
+    #[test]
+    fn a_split_room_is_isolated() {
+        // the first floor tile is on the left, the right half is walled off
+        let prefab = Prefab::parse("wwwwwww\nwxxwxxw\nwxxwxxw\nwxxwxxw\nwwDwwww").unwrap();
+        let rect = Rectangle { p1: Vector { x: 0, y: 0 }, p2: Vector { x: 7, y: 5 } };
+        let dungeon = dungeon(vec![(rect, Shape::Prefab(Arc::new(prefab)))]);
+
+        assert_eq!(cut_parts(&dungeon, &map(&dungeon)), Cut { rooms: vec![RoomId(0)], connections: Vec::new() });
+    }
+
+    #[test]
+    fn rooms_without_corridors_are_isolated() {
+        let room = |x| (Rectangle { p1: Vector { x, y: 0 }, p2: Vector { x: x + 5, y: 5 } }, Shape::Rectangle);
+        let dungeon = dungeon(vec![room(0), room(8), room(16)]);
+
+        assert_eq!(cut_parts(&dungeon, &map(&dungeon)).rooms, vec![RoomId(1), RoomId(2)]);
+    }
+
+    #[test]
+    fn a_corridor_leading_nowhere_is_cut() {
+        let room = |x| (Rectangle { p1: Vector { x, y: 0 }, p2: Vector { x: x + 5, y: 5 } }, Shape::Rectangle);
+        let mut dungeon = dungeon(vec![room(0), room(10), room(20)]);
+        let corridors = [
+            (0, 1, vec![Vector { x: 5, y: 2 }, Vector { x: 9, y: 2 }]),
+            (1, 2, vec![Vector { x: 15, y: 2 }, Vector { x: 19, y: 2 }]),
+            // in the void, away from both rooms
+            (0, 2, vec![Vector { x: 2, y: 8 }, Vector { x: 22, y: 8 }]),
+        ];
+
+        for (id, (from, to, waypoints)) in corridors.into_iter().enumerate() {
+            dungeon.connect_rooms(RoomId(from), RoomId(to));
+            dungeon.connections[id].as_mut().unwrap().path.waypoints = waypoints;
+        }
+
+        assert_eq!(cut_parts(&dungeon, &map(&dungeon)), Cut { rooms: Vec::new(), connections: vec![ConnectionId(2)] });
+    }
+}
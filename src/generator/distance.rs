@@ -0,0 +1,136 @@
+use super::layout::DungeonLayout;
+use super::math::Vector;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Cost of a step onto each kind of walkable tile, the other tiles cannot be
+/// walked on
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TileCosts {
+    pub floor: u32,
+    pub corridor: u32,
+    pub door: u32,
+}
+
+impl Default for TileCosts {
+    fn default() -> Self {
+        TileCosts {
+            floor: 1,
+            corridor: 1,
+            door: 1,
+        }
+    }
+}
+
+/// Walking distance from the nearest source to each tile of a map, `None`
+/// for the tiles which cannot be walked to. Positions are in map coordinates.
+#[derive(Clone, Debug)]
+pub struct DistanceMap {
+    pub width: u32,
+    pub height: u32,
+    distances: Vec<Option<u32>>,
+}
+
+impl DistanceMap {
+    /// Dijkstra search on a grid, `costs` holds the cost of a step onto each
+    /// tile, `None` for the tiles which cannot be walked on. Sources out of
+    /// the grid or on such tiles are ignored.
+    pub(super) fn new(width: u32, height: u32, costs: &[Option<u32>], sources: &[Vector<i32>]) -> DistanceMap {
+        let (w, h) = (width as i32, height as i32);
+        let mut distances = vec![None; costs.len()];
+        let mut open: BinaryHeap<Reverse<(u32, usize)>> = BinaryHeap::new();
+
+        for source in sources {
+            if source.x >= 0 && source.x < w && source.y >= 0 && source.y < h {
+                let n = (source.x + source.y * w) as usize;
+
+                if costs[n].is_some() {
+                    distances[n] = Some(0);
+                    open.push(Reverse((0, n)));
+                }
+            }
+        }
+
+        while let Some(Reverse((distance, n))) = open.pop() {
+            if distances[n].is_some_and(|d| distance > d) {
+                continue;
+            }
+
+            let (x, y) = ((n % width as usize) as i32, (n / width as usize) as i32);
+
+            for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                let (nx, ny) = (x + dx, y + dy);
+
+                if nx < 0 || nx >= w || ny < 0 || ny >= h {
+                    continue;
+                }
+
+                let m = (nx + ny * w) as usize;
+                let next = match costs[m] {
+                    Some(cost) => distance.saturating_add(cost),
+                    None => continue,
+                };
+
+                if distances[m].is_none_or(|d| next < d) {
+                    distances[m] = Some(next);
+                    open.push(Reverse((next, m)));
+                }
+            }
+        }
+
+        DistanceMap { width, height, distances }
+    }
+
+    /// Distance of a tile, `None` out of the map
+    pub fn get(&self, position: &Vector<i32>) -> Option<u32> {
+        if position.x < 0 || position.y < 0 || position.x >= self.width as i32 || position.y >= self.height as i32 {
+            return None;
+        }
+
+        self.distances[position.x as usize + position.y as usize * self.width as usize]
+    }
+
+    /// Distance of every tile, row after row like `Map::to_bytes`
+    pub fn distances(&self) -> &[Option<u32>] {
+        &self.distances
+    }
+
+    /// Tile the farthest from the sources, with its distance, the first one
+    /// in reading order on a tie
+    pub fn farthest(&self) -> Option<(Vector<i32>, u32)> {
+        let width = self.width as usize;
+
+        self.distances.iter().enumerate()
+            .filter_map(|(n, d)| d.map(|d| (n, d)))
+            .max_by_key(|(n, d)| (*d, Reverse(*n)))
+            .map(|(n, d)| (Vector { x: (n % width) as i32, y: (n / width) as i32 }, d))
+    }
+
+    /// Distance of each room of the layout, to the nearest tile within its
+    /// bounds, in the order of `DungeonLayout::rooms`
+    pub fn room_distances(&self, layout: &DungeonLayout) -> Vec<Option<u32>> {
+        layout.rooms.iter()
+            .map(|room| {
+                let bounds = &room.bounds;
+
+                (bounds.p1.y..bounds.p2.y)
+                    .flat_map(|y| (bounds.p1.x..bounds.p2.x).map(move |x| Vector { x, y }))
+                    .filter_map(|p| self.get(&p))
+                    .min()
+            })
+            .collect()
+    }
+
+    /// Id of the room the farthest from the sources, with its distance, the
+    /// first one of the layout on a tie. Rooms which cannot be walked to are
+    /// left aside.
+    pub fn farthest_room(&self, layout: &DungeonLayout) -> Option<(usize, u32)> {
+        self.room_distances(layout).into_iter()
+            .zip(layout.rooms.iter())
+            .enumerate()
+            .filter_map(|(i, (d, room))| d.map(|d| (i, room.id, d)))
+            .max_by_key(|(i, _, d)| (*d, Reverse(*i)))
+            .map(|(_, id, d)| (id, d))
+    }
+}
@@ -2,41 +2,62 @@ mod room;
 mod connection;
 mod path;
 
-use super::errors::PlacementError;
+use super::errors::{GenerationError, PlacementError};
+use super::layout::{ConnectionLayout, DungeonLayout, PrefabLayout, RoomLayout};
 use super::map::Map;
-use super::math::{Rectangle, Vector};
-use room::Room;
-use rand::{seq::SliceRandom, Rng};
+use super::router::CorridorRouter;
+use super::math::{Direction, Rectangle, Vector};
+use super::prefab::Prefab;
+use super::shape::{Shape, ShapeWeights, APPROACH};
+use super::spatial::SpatialIndex;
+use super::random;
+pub use room::Room;
+use connection::Connection;
+use rand::seq::SliceRandom;
 use rand_chacha::ChaCha8Rng;
 use std::cmp;
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::sync::Arc;
 
+/// Index of a room in `Dungeon::rooms`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RoomId(pub usize);
+
+/// Index of a connection in `Dungeon::connections`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConnectionId(pub usize);
+
+#[derive(Clone)]
 pub struct Dungeon {
-    pub min_size: Vector<u8>,
-    pub max_size: Vector<u8>,
-    pub rooms_spacing: (u8, u8),
-    pub path_extension: (u8, u8),
-    pub rooms: Vec<Rc<RefCell<Room>>>,
+    pub min_size: Vector<u32>,
+    pub max_size: Vector<u32>,
+    pub rooms_spacing: (u32, u32),
+    pub path_extension: (u32, u32),
+    pub shapes: ShapeWeights,
+    pub prefabs: Vec<Arc<Prefab>>,
+    pub rooms: Vec<Room>,
+    /// Connections between the rooms, `None` once removed so the ids of the
+    /// other ones stay valid
+    pub connections: Vec<Option<Connection>>,
+    /// Bounds of the rooms, to test the overlaps without scanning them all
+    pub index: SpatialIndex,
     pub rng: ChaCha8Rng,
 }
 
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-}
+// dungeons may be generated on other threads, keep them free of `Rc` & `RefCell`
+const _: fn() = || {
+    fn send<T: Send>() {}
+    send::<Dungeon>();
+};
 
 impl Dungeon {
-    pub fn find_empty_space(&self, size: Vector<i8>) -> Result<Rectangle, PlacementError> {
+    pub fn find_empty_space(&self, size: Vector<i32>, shape: &Shape) -> Result<Rectangle, PlacementError> {
         let mut rng = self.rng.clone();
         let mut indices: Vec<usize> = (0..self.rooms.len()).collect();
 
         indices.shuffle(&mut rng);
 
         for index in indices {
-            let room = self.rooms[index].borrow();
+            let room = &self.rooms[index];
             let mut directions: Vec<Direction> = vec![
                 Direction::North,
                 Direction::South,
@@ -49,7 +70,7 @@ impl Dungeon {
             for direction in directions {
                 let rect = self.get_rectangle(room.rect.clone(), size.clone(), direction);
 
-                if !self.overlap_test(&rect) {
+                if !self.overlap_test(&rect, shape) {
                     return Ok(rect);
                 }
             }
@@ -58,15 +79,15 @@ impl Dungeon {
         Err(PlacementError::new("Cannot find a valid position"))
     }
 
-    fn get_rectangle(&self, rect: Rectangle, size: Vector<i8>, direction: Direction) -> Rectangle {
+    fn get_rectangle(&self, rect: Rectangle, size: Vector<i32>, direction: Direction) -> Rectangle {
         let mut p1 = rect.p1;
-        let spacing = self.rng.clone().gen_range(self.rooms_spacing.0..self.rooms_spacing.1) as i8;
+        let spacing = random::gen_range(&mut self.rng.clone(), self.rooms_spacing.0..self.rooms_spacing.1) as i32;
 
         match direction {
             Direction::North => p1.y = rect.p2.y + spacing,
             Direction::East  => p1.x = rect.p2.x + spacing,
-            Direction::South => p1.y = p1.y - (spacing + size.y),
-            Direction::West  => p1.x = p1.x - (spacing + size.x),
+            Direction::South => p1.y -= spacing + size.y,
+            Direction::West  => p1.x -= spacing + size.x,
         }
 
         // align the point to even cells on grid
@@ -84,90 +105,218 @@ impl Dungeon {
         }
     }
 
-    pub fn to_map(&self) -> Map {
+    pub fn to_map(&self) -> Result<Map, GenerationError> {
         let mut min = Vector { x: 0, y: 0 };
         let mut max = Vector { x: 0, y: 0 };
 
-        for r in self.rooms.iter() {
-            let room = r.borrow_mut();
-
+        for room in self.rooms.iter() {
             (min, max) = Dungeon::get_min_max(min, max, &room.rect.p1, &room.rect.p2);
+        }
 
-            for connection_ref in room.connections.iter() {
-                let connection = connection_ref.borrow_mut();
-
-                for waypoint in connection.path.waypoints.iter() {
-                    (min, max) = Dungeon::get_min_max(min, max, waypoint, waypoint);
-                }
-
+        for connection in self.connections.iter().flatten() {
+            for waypoint in connection.path.waypoints.iter() {
+                (min, max) = Dungeon::get_min_max(min, max, waypoint, waypoint);
             }
         }
 
+        // keep a border around the dungeon for the walls
+        let overflow = || GenerationError::CoordinateOverflow;
+        let min = Vector { x: min.x.checked_sub(1).ok_or_else(overflow)?, y: min.y.checked_sub(1).ok_or_else(overflow)? };
+        let max = Vector { x: max.x.checked_add(1).ok_or_else(overflow)?, y: max.y.checked_add(1).ok_or_else(overflow)? };
+
         let mut map = Map::build();
 
-        map.resize(&min, &max);
+        map.resize(&min, &max)?;
 
         for room in self.rooms.iter() {
-            map.add_room(&room.borrow().rect);
-            for connection in &room.borrow().connections {
-                let waypoints = &connection.borrow().path.waypoints;
-                let len = waypoints.len();
-                for i in 0..len {
-                    if i == len - 1 {
+            map.add_room(&room.rect, &room.shape);
+        }
+
+        // after every room, so no floor is drawn over the doors
+        for connection in self.connections.iter().flatten() {
+            let waypoints = &connection.path.waypoints;
+            let len = waypoints.len();
+            for i in 0..len {
+                if i == len - 1 {
+                    map.add_door(&waypoints[i]);
+                    break;
+                }
+                else {
+                    map.add_corridor(&waypoints[i], &waypoints[i + 1]);
+
+                    if i == 0 {
                         map.add_door(&waypoints[i]);
-                        break;
                     }
-                    else {
-                        map.add_corridor(&waypoints[i], &waypoints[i + 1]);
+                }
+            }
+        }
 
-                        if i == 0 {
-                            map.add_door(&waypoints[i]);
-                        }
-                    }
+        // corridors running through a room cannot go through its walls
+        for room in self.rooms.iter() {
+            for wall in room.shape.walls(&room.rect) {
+                map.add_wall(&wall);
+            }
+        }
+
+        map.add_crossings();
+
+        Ok(map)
+    }
+
+    /// Describe the rooms and connections of the dungeon, translated by
+    /// `offset` to match the coordinates of the map
+    pub fn to_layout(&self, offset: &Vector<i32>) -> DungeonLayout {
+        let translate = |p: &Vector<i32>| p.clone() + offset.clone();
+        let mut rooms = Vec::new();
+        let mut connections = Vec::new();
+        let mut prefabs = Vec::new();
+
+        for room in self.rooms.iter() {
+            if let Shape::Prefab(prefab) = &room.shape {
+                prefabs.push(PrefabLayout { room: room.id.0, name: prefab.name.clone() });
+            }
+
+            rooms.push(RoomLayout {
+                id: room.id.0,
+                bounds: Rectangle { p1: translate(&room.rect.p1), p2: translate(&room.rect.p2) },
+                shape: room.shape.kind(),
+            });
+
+            // connections are shared by both rooms, only keep them once
+            for connection in room.connections.iter().filter_map(|id| self.connection(*id)) {
+                if connection.from != room.id || connection.path.waypoints.is_empty() {
+                    continue;
                 }
+
+                // waypoints are stored from the destination to the origin
+                let waypoints: Vec<Vector<i32>> = connection.path.waypoints.iter().rev().map(translate).collect();
+
+                connections.push(ConnectionLayout {
+                    id: connections.len(),
+                    from: connection.from.0,
+                    to: connection.to.0,
+                    from_door: waypoints[0].clone(),
+                    to_door: waypoints[waypoints.len() - 1].clone(),
+                    waypoints,
+                });
             }
         }
 
-        map
+        DungeonLayout { rooms, connections, prefabs, repairs: Vec::new() }
     }
 
-    pub fn connect_rooms(&mut self, first: usize, second: usize) -> bool {
-        Room::connect(&self.rooms[first], &self.rooms[second])
+    /// Connect two rooms, returns false when they are already connected
+    pub fn connect_rooms(&mut self, first: RoomId, second: RoomId) -> bool {
+        if self.connections.iter().flatten().any(|c| c.is_between(first, second)) {
+            return false;
+        }
+
+        let id = ConnectionId(self.connections.len());
+
+        self.connections.push(Some(Connection::new(first, second)));
+        self.rooms[first.0].connections.push(id);
+        self.rooms[second.0].connections.push(id);
+
+        true
     }
 
-    pub fn add_room(&mut self, id: usize, rect: Rectangle) {
-        let room = Room {
+    pub fn disconnect_rooms(&mut self, first: RoomId, second: RoomId) {
+        for slot in self.connections.iter_mut() {
+            if slot.as_ref().is_some_and(|c| c.is_between(first, second)) {
+                *slot = None;
+            }
+        }
+
+        let connections = &self.connections;
+
+        for id in [first, second] {
+            self.rooms[id.0].connections.retain(|c| connections[c.0].is_some());
+        }
+    }
+
+    pub fn add_room(&mut self, rect: Rectangle, shape: Shape) -> RoomId {
+        let id = RoomId(self.rooms.len());
+
+        self.index.insert(rect.clone());
+        self.rooms.push(Room {
             id,
             rect,
+            shape,
             connections: Vec::new(),
-        };
-        self.rooms.push(Rc::new(RefCell::new(room)));
+        });
+
+        id
     }
 
-    pub fn make_paths(&mut self) {
-        for room in self.rooms.iter() {
-            for connection in room.borrow().connections.iter() {
-                connection.borrow_mut().make_path(&mut self.rng, self.path_extension);
-            }
-        }
+    pub fn connection(&self, id: ConnectionId) -> Option<&Connection> {
+        self.connections[id.0].as_ref()
     }
 
-    fn overlap_test(&self, rect: &Rectangle) -> bool {
-        let mut overlap = false;
+    /// Trace the corridors of every connection, returns the (from, to) room
+    /// ids of the connections which could not be traced
+    pub fn make_paths(&mut self, router: &mut dyn CorridorRouter) -> Vec<(RoomId, RoomId)> {
+        let mut traced = vec![false; self.connections.len()];
+        let mut unsolved = Vec::new();
+
+        // rooms first, so the corridors are traced in the same order as the
+        // connections were made for each room
+        for room in 0..self.rooms.len() {
+            for index in 0..self.rooms[room].connections.len() {
+                let id = self.rooms[room].connections[index];
+
+                // connections are shared by both rooms, only trace them once
+                if traced[id.0] {
+                    continue;
+                }
 
-        for r in self.rooms.iter() {
-            let room = r.borrow();
-            overlap = room.rect.overlap(&rect);
+                traced[id.0] = true;
 
-            if overlap {
-                break;
+                if self.trace(id, router).is_err() {
+                    if let Some(connection) = self.connection(id) {
+                        unsolved.push((connection.from, connection.to));
+                    }
+                }
             }
         }
 
-        overlap
+        unsolved
+    }
+
+    /// Trace the corridor of the connection between two rooms, if not done yet
+    pub fn make_path(&mut self, from: RoomId, to: RoomId, router: &mut dyn CorridorRouter) -> Result<(), GenerationError> {
+        let id = self.rooms[from.0].connections.iter()
+            .copied()
+            .find(|id| self.connection(*id).is_some_and(|c| c.from == from && c.to == to));
+
+        match id {
+            Some(id) => self.trace(id, router),
+            None => Err(GenerationError::PathUnsolvable { from: from.0, to: to.0 }),
+        }
+    }
+
+    fn trace(&mut self, id: ConnectionId, router: &mut dyn CorridorRouter) -> Result<(), GenerationError> {
+        let connection = match self.connections[id.0].as_mut() {
+            Some(connection) => connection,
+            None => return Ok(()),
+        };
+        let (from, to) = (&self.rooms[connection.from.0], &self.rooms[connection.to.0]);
+
+        connection.make_path(from, to, &mut self.rng, self.path_extension, router, &self.index)
+    }
+
+    fn overlap_test(&self, rect: &Rectangle, shape: &Shape) -> bool {
+        // the approaches of the prefabs stick out of their bounds
+        let area = Rectangle {
+            p1: Vector { x: rect.p1.x - APPROACH, y: rect.p1.y - APPROACH },
+            p2: Vector { x: rect.p2.x + APPROACH, y: rect.p2.y + APPROACH },
+        };
+
+        self.index.query(&area).into_iter()
+            .map(|i| &self.rooms[i])
+            .any(|room| Shape::overlap((&room.rect, &room.shape), (rect, shape)))
     }
 
-    fn get_min_max(mut min: Vector<i8>, mut max: Vector<i8>, p1: &Vector<i8>, p2: &Vector<i8>) -> (Vector<i8>, Vector<i8>) {
+    fn get_min_max(mut min: Vector<i32>, mut max: Vector<i32>, p1: &Vector<i32>, p2: &Vector<i32>) -> (Vector<i32>, Vector<i32>) {
 
         min.x = cmp::min(p1.x, min.x);
         min.y = cmp::min(p1.y, min.y);
@@ -177,3 +326,187 @@ impl Dungeon {
         (min, max)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::router::{Route, RotationRouter};
+    use crate::generator::strategy;
+    use crate::Config;
+    use rand::SeedableRng;
+    use std::thread;
+
+    const DOOR: u8 = 3;
+
+    /// Bounds of the two rooms of a connection
+    type Ends = (Vector<i32>, Vector<i32>, Vector<i32>, Vector<i32>);
+
+    /// Router giving the same answer to every route, and keeping the bounds of
+    /// the rooms it was asked to join
+    struct Recorder {
+        path: Option<Vec<Vector<i32>>>,
+        routes: Vec<Ends>,
+    }
+
+    impl CorridorRouter for Recorder {
+        fn route(&mut self, route: &Route, _: &mut ChaCha8Rng) -> Option<Vec<Vector<i32>>> {
+            self.routes.push((route.from_room.p1.clone(), route.from_room.p2.clone(), route.to_room.p1.clone(), route.to_room.p2.clone()));
+            self.path.clone()
+        }
+    }
+
+    /// Dungeon of the config with its rooms placed and connected, before the
+    /// corridors are traced
+    fn dungeon(config: &Config) -> Dungeon {
+        let mut dungeon = Dungeon {
+            min_size: config.rooms_min_size.clone(),
+            max_size: config.rooms_max_size.clone(),
+            rooms_spacing: (config.rooms_spacing.min, config.rooms_spacing.max),
+            path_extension: (config.path_extension.min, config.path_extension.max),
+            shapes: config.shapes,
+            prefabs: Vec::new(),
+            rooms: Vec::new(),
+            connections: Vec::new(),
+            index: SpatialIndex::default(),
+            rng: ChaCha8Rng::seed_from_u64(config.seed),
+        };
+
+        strategy::place_rooms(&mut dungeon, config).unwrap();
+        dungeon
+    }
+
+    #[test]
+    fn rooms_sharing_their_bounds_keep_a_gap_between_their_floors() {
+        let shapes = ShapeWeights { rectangle: 0, ellipse: 1, cross: 1, l_shape: 1, pillars: 0, composite: 1 };
+
+        for seed in 0..100 {
+            let dungeon = dungeon(&Config::builder().seed(seed).rooms_count(40).rooms_spacing(2, 3).shapes(shapes).build().unwrap());
+
+            for (n, a) in dungeon.rooms.iter().enumerate() {
+                for b in dungeon.rooms[..n].iter().filter(|b| b.rect.overlap(&a.rect)) {
+                    let gap = a.shape.cells(&a.rect)
+                        .flat_map(|p| b.shape.cells(&b.rect).map(move |q| (p.x - q.x).abs().max((p.y - q.y).abs())))
+                        .min();
+
+                    // a wall on each side of a corridor between the floors
+                    assert!(gap > Some(3), "Seed {}: rooms {} & {} are {:?} cells apart", seed, b.id.0, a.id.0, gap);
+                }
+            }
+        }
+    }
+
+    /// Dungeon of the seed with its first connection removed, leaving a `None`
+    /// slot, along with the rooms it joined
+    fn disconnected(seed: u64) -> (Dungeon, (RoomId, RoomId)) {
+        let mut dungeon = dungeon(&Config { seed, ..Config::default() });
+        let first = dungeon.connections.iter().flatten().next().map(|c| (c.from, c.to)).unwrap();
+
+        dungeon.disconnect_rooms(first.0, first.1);
+
+        (dungeon, first)
+    }
+
+    fn ends(dungeon: &Dungeon, connection: &Connection) -> Ends {
+        let (from, to) = (&dungeon.rooms[connection.from.0].rect, &dungeon.rooms[connection.to.0].rect);
+
+        (from.p1.clone(), from.p2.clone(), to.p1.clone(), to.p2.clone())
+    }
+
+    fn router() -> RotationRouter {
+        RotationRouter { path_extension: (Config::default().path_extension.min, Config::default().path_extension.max) }
+    }
+
+    #[test]
+    fn paths_are_traced_once_per_connection() {
+        for seed in 0..20 {
+            let (mut dungeon, _) = disconnected(seed);
+            let mut expected: Vec<Ends> = dungeon.connections.iter().flatten().map(|c| ends(&dungeon, c)).collect();
+            let mut recorder = Recorder { path: Some(Vec::new()), routes: Vec::new() };
+
+            assert!(dungeon.connections.iter().any(Option::is_none));
+            assert_eq!(dungeon.make_paths(&mut recorder), vec![]);
+
+            expected.sort();
+            recorder.routes.sort();
+            assert_eq!(recorder.routes, expected, "Seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn unsolved_paths_are_reported_once_per_connection() {
+        for seed in 0..20 {
+            let (mut dungeon, _) = disconnected(seed);
+            let mut expected: Vec<(RoomId, RoomId)> = dungeon.connections.iter().flatten().map(|c| (c.from, c.to)).collect();
+            let mut unsolved = dungeon.make_paths(&mut Recorder { path: None, routes: Vec::new() });
+
+            expected.sort();
+            unsolved.sort();
+            assert_eq!(unsolved, expected, "Seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn layouts_and_maps_hold_each_connection_once() {
+        for seed in 0..20 {
+            let mut traced = dungeon(&Config { seed, ..Config::default() });
+
+            traced.make_paths(&mut router());
+
+            let removed = traced.connections.iter().position(|c| c.as_ref().is_some_and(|c| !c.path.waypoints.is_empty())).unwrap();
+            let (from, to) = traced.connection(ConnectionId(removed)).map(|c| (c.from, c.to)).unwrap();
+
+            // a removed connection draws nothing, as if it was never traced
+            let mut dungeon = traced.clone();
+            let mut untraced = traced.clone();
+
+            dungeon.disconnect_rooms(from, to);
+            untraced.connections[removed].as_mut().unwrap().path.waypoints.clear();
+
+            let map = dungeon.to_map().unwrap();
+            let bytes = map.to_bytes();
+            let width = map.size().0 as i32;
+
+            assert_eq!(bytes, untraced.to_map().unwrap().to_bytes(), "Seed {}", seed);
+
+            let layout = dungeon.to_layout(map.offset());
+            let mut expected: Vec<(usize, usize)> = dungeon.connections.iter().flatten()
+                .filter(|c| !c.path.waypoints.is_empty())
+                .map(|c| (c.from.0, c.to.0))
+                .collect();
+            let mut connections: Vec<(usize, usize)> = layout.connections.iter().map(|c| (c.from, c.to)).collect();
+
+            expected.sort();
+            connections.sort();
+            assert_eq!(connections, expected, "Seed {}", seed);
+
+            for connection in layout.connections.iter() {
+                for door in [&connection.from_door, &connection.to_door] {
+                    assert_eq!(bytes[(door.x + door.y * width) as usize], DOOR, "Seed {}: no door at {:?}", seed, door);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dungeons_and_maps_move_to_other_threads() {
+        let config = Config { seed: 3, ..Config::default() };
+        let mut here = dungeon(&config);
+
+        here.make_paths(&mut router());
+
+        let there = dungeon(&config);
+        let (there, map) = thread::spawn(move || {
+            let mut dungeon = there;
+
+            dungeon.make_paths(&mut router());
+
+            let map = dungeon.to_map().unwrap();
+
+            (dungeon, map)
+        }).join().unwrap();
+        let bytes = thread::spawn(move || map.to_bytes()).join().unwrap();
+
+        assert_eq!(bytes, here.to_map().unwrap().to_bytes());
+        assert_eq!(there.to_map().unwrap().to_bytes(), bytes);
+    }
+}
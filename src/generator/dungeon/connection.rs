@@ -1,25 +1,25 @@
-extern crate nalgebra as na;
-extern crate approx;
-
-use crate::generator::math::{Rectangle, Vector, intersects};
+use crate::generator::math::{Direction, Vector};
+use crate::generator::errors::GenerationError;
+use crate::generator::random;
+use crate::generator::router::{CorridorRouter, Route};
+use crate::generator::spatial::SpatialIndex;
 use super::room::Room;
 use super::path::Path;
-use std::rc::{Weak, Rc};
-use std::cell::RefCell;
-use std::cmp;
+use super::RoomId;
 use rand_chacha::ChaCha8Rng;
-use rand::{seq::SliceRandom, Rng};
-use na::{Point2, Rotation2};
-use approx::relative_eq;
+use rand::seq::SliceRandom;
+
+const MAX_ATTEMPTS: usize = 4;
 
+#[derive(Clone)]
 pub struct Connection {
-    from: Weak<RefCell<Room>>,
-    to: Weak<RefCell<Room>>,
+    pub from: RoomId,
+    pub to: RoomId,
     pub path: Path,
 }
 
 impl Connection {
-    pub fn new(from: Weak<RefCell<Room>>, to: Weak<RefCell<Room>>) -> Connection {
+    pub fn new(from: RoomId, to: RoomId) -> Connection {
         Connection {
             from,
             to,
@@ -27,132 +27,63 @@ impl Connection {
         }
     }
 
-    pub fn has_destination(&self, room: &Rc<RefCell<Room>>) -> bool {
-        Rc::ptr_eq(&self.to.upgrade().unwrap(), room)
-    }
-
-    pub fn make_path(&mut self, rng: &mut ChaCha8Rng, path_extension: (u8, u8)) {
-        if self.path.waypoints.len() <= 0 {
-            let (from_pos, from_dir) = Connection::create_room_exit(rng, &self.from, &self.to, path_extension).unwrap();
-            let (to_pos, _to_dir) = Connection::create_room_exit(rng, &self.to, &self.from, path_extension).unwrap();
-
-            let mut path = Connection::find_path(
-                from_pos.clone(),
-                &self.from.upgrade().unwrap().borrow().rect,
-                from_dir.clone(),
-                to_pos.clone(),
-                &self.to.upgrade().unwrap().borrow().rect,
-                path_extension,
-                rng,
-            );
-
-            self.path.waypoints.push(to_pos);
-            self.path.waypoints.append(&mut path);
-            self.path.waypoints.push(from_pos);
-        }
+    /// Whether the connection joins both rooms, in either direction
+    pub fn is_between(&self, a: RoomId, b: RoomId) -> bool {
+        (self.from == a && self.to == b) || (self.from == b && self.to == a)
     }
 
-    /// Find a path going from one point to another, avoiding penetration into the destination room
-    fn find_path(from_pos: Vector<i8>, from_rect: &Rectangle, from_dir: Direction, to_pos: Vector<i8>, to_rect: &Rectangle, path_extension: (u8, u8), rng: &mut ChaCha8Rng) -> Vec<Vector<i8>> {
-        let rot = match from_dir {
-            Direction::North => Rotation2::identity(),
-            Direction::South => Rotation2::new(std::f32::consts::FRAC_PI_2 * 2.0),
-            Direction::East => Rotation2::new(-std::f32::consts::FRAC_PI_2),
-            Direction::West => Rotation2::new(std::f32::consts::FRAC_PI_2),
-        };
-        let pos_from = rot * Point2::new(from_pos.x as f32, from_pos.y as f32);
-        let pos_to = rot * Point2::new(to_pos.x as f32, to_pos.y as f32);
-        let mut path = Vec::new();
-
-        match Connection::find_next_waypoint(&mut path, pos_from, pos_to, from_rect, to_rect, rot.inverse(), 0, path_extension, rng) {
-            _ => path,
-        }
-    }
-
-    /// Find the next waypoint of the path.
-    /// This method assumes points are rotated toward North (Y-)
-    fn find_next_waypoint(path: &mut Vec<Vector<i8>>, pos_from: Point2<f32>, pos_to: Point2<f32>, rect_from: &Rectangle, rect_to: &Rectangle, inv: Rotation2<f32>, iteration: i8, path_extension: (u8, u8), rng: &mut ChaCha8Rng) -> Option<bool> {
-        if iteration > 10 {
-            return None;
-        }
-        let delta = pos_from.y - pos_to.y;
-        let mut pos_next = Point2::new(pos_from.x, pos_from.y);
-
-        if delta <= 0f32 {
-            pos_next.y -= rng.gen_range(path_extension.0..path_extension.1) as f32;
-            pos_next.y = if pos_next.y.round() as i32 % 2 == 0 { pos_next.y } else { pos_next.y + 1f32 };
-        }
-        else if iteration > 0
-        {
-            pos_next.y -= delta;
-            pos_next.y = if pos_next.y.round() as i32 % 2 == 0 { pos_next.y } else { pos_next.y + 1f32 };
-
-            // offset values to avoid collision with a room when the from or
-            // to positions are within the room (entrance & exit)
-            let from = cmp::min(pos_from.y.round() as i8, pos_to.y.round() as i8) + 1;
-            let to = cmp::max(pos_from.y.round() as i8, pos_to.y.round() as i8) - 1;
-            let p1 = inv * Point2::new(pos_from.x, from as f32);
-            let p2 = inv * Point2::new(pos_from.x, to as f32);
-
-            if intersects(p1, p2, rect_to, rect_from) {
-                return Some(false);
-            } else if relative_eq!(Point2::new(pos_next.x.round(), pos_next.y.round()), Point2::new(pos_to.x.round(), pos_to.y.round())) {
-                return Some(true);
-            }
+    /// Trace the corridor of the connection, new exits are tried up to
+    /// `MAX_ATTEMPTS` times before giving up. `from` & `to` are the rooms of
+    /// the connection.
+    pub fn make_path(&mut self, from: &Room, to: &Room, rng: &mut ChaCha8Rng, path_extension: (u32, u32), router: &mut dyn CorridorRouter, index: &SpatialIndex) -> Result<(), GenerationError> {
+        if !self.path.waypoints.is_empty() {
+            return Ok(());
         }
 
-        let rot_delta = (pos_from.x - pos_to.x).round();
-        let next_rot = if relative_eq!(rot_delta.round(), 0f32) {
-            if rng.gen_range(0..1) == 0 {
-                Rotation2::new(-std::f32::consts::FRAC_PI_2)
-            }
-            else {
-                Rotation2::new(std::f32::consts::FRAC_PI_2)
-            }
-        }
-        else if rot_delta < 0f32 {
-            Rotation2::new(-std::f32::consts::FRAC_PI_2)
-        } else {
-            Rotation2::new(std::f32::consts::FRAC_PI_2)
-        };
+        for _ in 0..MAX_ATTEMPTS {
+            let exits = Connection::create_room_exit(rng, from, to, path_extension)
+                .zip(Connection::create_room_exit(rng, to, from, path_extension));
 
-        let mut retry = 0;
+            let ((from_pos, from_dir), (to_pos, to_dir)) = match exits {
+                Some(exits) => exits,
+                None => continue,
+            };
 
-        loop {
-            match Connection::find_next_waypoint(path, next_rot * pos_next, next_rot * pos_to, rect_from, rect_to, inv * next_rot.inverse(), iteration + 1, path_extension, rng) {
-                Some(true) => {
-                    let world_point = inv * pos_next;
-                    let pos = Vector { x: world_point.x.round() as i8, y: world_point.y.round() as i8 };
-                    path.push(pos);
-                    break;
-                },
-                Some(false) => {
-                    pos_next.y -= rng.gen_range(path_extension.0..path_extension.1) as f32;
-                    pos_next.y = if pos_next.y.round() as i32 % 2 == 0 { pos_next.y } else { pos_next.y + 1f32 };
-                }
-                _ => {
-                    return None;
+            let path = router.route(
+                &Route {
+                    from: from_pos.clone(),
+                    from_direction: from_dir,
+                    from_room: &from.rect,
+                    to: to_pos.clone(),
+                    to_direction: to_dir,
+                    to_room: &to.rect,
+                    rooms: index.rects(),
+                    index,
                 },
-            }
+                rng,
+            );
 
-            retry += 1;
+            // waypoints are stored from the destination to the origin
+            if let Some(path) = path {
+                self.path.waypoints.push(to_pos);
+                self.path.waypoints.extend(path.into_iter().rev());
+                self.path.waypoints.push(from_pos);
 
-            if retry > 5 {
-                return None;
+                return Ok(());
             }
         }
 
-        Some(true)
+        Err(GenerationError::PathUnsolvable { from: self.from.0, to: self.to.0 })
     }
 
     /// Create an exit on one wall of a room, the exit cannot face the other room.
-    /// The exit will always be on a wall, on an even tile of the grid and cannot be a corner
-    fn create_room_exit(rng: &mut ChaCha8Rng, room: &Weak<RefCell<Room>>, other_room: &Weak<RefCell<Room>>, path_extension: (u8, u8)) -> Option<(Vector<i8>, Direction)> {
-        let rect = room.upgrade().unwrap().borrow().rect.clone();
-        let other_rect = other_room.upgrade().unwrap().borrow().rect.clone();
+    /// The exit will always be on a wall, on an even tile of the grid, and on a
+    /// tile of the room shape
+    fn create_room_exit(rng: &mut ChaCha8Rng, room: &Room, other_room: &Room, path_extension: (u32, u32)) -> Option<(Vector<i32>, Direction)> {
+        let (rect, shape, other_rect) = (&room.rect, &room.shape, &other_room.rect);
         let coords = Vector {
-            x: rng.gen_range((rect.p1.x + 1)..(rect.p2.x - 2)),
-            y: rng.gen_range((rect.p1.y + 1)..(rect.p2.y - 2)),
+            x: random::gen_range(rng, (rect.p1.x + 1)..(rect.p2.x - 2)),
+            y: random::gen_range(rng, (rect.p1.y + 1)..(rect.p2.y - 2)),
         };
 
         // ... and avoid corner coords
@@ -182,7 +113,15 @@ impl Connection {
             position.x = if position.x % 2 == 0 { position.x } else { position.x - 1 };
             position.y = if position.y % 2 == 0 { position.y } else { position.y - 1 };
 
-            let limit = (path_extension.1 + 1) as i8;
+            // move the exit along the wall when it is not part of the room
+            if !shape.is_exit(rect, &position, direction) {
+                position = match shape.nearest_exit(rect, &position, direction) {
+                    Some(position) => position,
+                    None => continue,
+                };
+            }
+
+            let limit = (path_extension.1 + 1) as i32;
             let test_point = match direction {
                 Direction::North => Vector { x: position.x, y: position.y - limit },
                 Direction::South => Vector { x: position.x, y: position.y + limit },
@@ -198,11 +137,3 @@ impl Connection {
         None
     }
 }
-
-#[derive(Clone, Debug)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-}
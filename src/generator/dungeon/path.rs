@@ -1,7 +1,8 @@
 use crate::generator::math::Vector;
 
+#[derive(Clone)]
 pub struct Path {
-    pub waypoints: Vec<Vector<i8>>,
+    pub waypoints: Vec<Vector<i32>>,
 }
 
 impl Path {
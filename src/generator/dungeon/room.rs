@@ -1,30 +1,14 @@
 use crate::generator::math::Rectangle;
-use super::connection::Connection;
-use std::rc::Rc;
-use std::cell::RefCell;
+use crate::generator::shape::Shape;
+use super::{ConnectionId, RoomId};
 
+#[derive(Clone)]
 pub struct Room {
-    pub id: usize,
+    pub id: RoomId,
+    /// Bounds of the room
     pub rect: Rectangle,
-    pub connections: Vec<Rc<RefCell<Connection>>>,
-}
-
-impl Room {
-    pub fn is_connected_to(&self, room: &Rc<RefCell<Room>>) -> bool {
-        self.connections.iter().any(|c| c.borrow().has_destination(room))
-    }
-
-    pub fn connect(a: &Rc<RefCell<Room>>, b: &Rc<RefCell<Room>>) -> bool {
-        if a.borrow().is_connected_to(b)
-            || b.borrow().is_connected_to(a) {
-                return false;
-        }
-
-        let connection = Rc::new(RefCell::new(Connection::new(Rc::downgrade(a).clone(), Rc::downgrade(b).clone())));
-
-        a.borrow_mut().connections.push(connection.clone());
-        b.borrow_mut().connections.push(connection.clone());
-
-        true
-    }
+    pub shape: Shape,
+    /// Connections starting from or leading to the room, in the order they
+    /// were made
+    pub connections: Vec<ConnectionId>,
 }
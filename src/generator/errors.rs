@@ -24,3 +24,90 @@ impl Error for PlacementError {
         &self.details
     }
 }
+
+/// Problem found in a `Config` by `Config::validate`, `field` is the path of
+/// the offending field, e.g. `rooms_min_size.x`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The `min..max` range of random values is empty
+    EmptyRange { field: &'static str, min: u32, max: u32 },
+    /// The min bound of an inclusive range is bigger than its max bound
+    MinAboveMax { field: &'static str, min: u32, max: u32 },
+    /// The value is below the smallest one the generation supports
+    TooSmall { field: &'static str, value: u32, min: u32 },
+    /// The rooms leave no cell for a door between their corners
+    RoomTooSmall { field: &'static str, value: u32, min: u32 },
+    /// The value is above the biggest one the generation supports
+    TooBig { field: &'static str, value: u32, max: u32 },
+    /// The count or every weight of the field is zero
+    Zero { field: &'static str },
+    /// The code of an enum of the config matches none of its variants
+    UnknownCode { field: &'static str, value: u32 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::EmptyRange { field, min, max } => write!(f, "{} range {}..{} is empty", field, min, max),
+            ConfigError::MinAboveMax { field, min, max } => write!(f, "min {} ({}) is bigger than max {} ({})", field, min, field, max),
+            ConfigError::TooSmall { field, value, min } => write!(f, "{} ({}) cannot be smaller than {}", field, value, min),
+            ConfigError::RoomTooSmall { field, value, min } => write!(f, "{} ({}) cannot be smaller than {}, doors are placed at least one cell away from the corners", field, value, min),
+            ConfigError::TooBig { field, value, max } => write!(f, "{} ({}) cannot be bigger than {}", field, value, max),
+            ConfigError::Zero { field } => write!(f, "{} cannot be zero", field),
+            ConfigError::UnknownCode { field, value } => write!(f, "{} ({}) is not a known code", field, value),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationError {
+    /// The configuration cannot produce a dungeon
+    InvalidConfig(String),
+    /// No free space was found around the existing rooms to place a new one
+    PlacementExhausted { room: usize },
+    /// No corridor could be traced between two connected rooms
+    PathUnsolvable { from: usize, to: usize },
+    /// The dungeon grew beyond the representable coordinates
+    CoordinateOverflow,
+}
+
+impl GenerationError {
+    /// Numeric code of the error, as exposed through the C API
+    pub fn code(&self) -> i32 {
+        match self {
+            GenerationError::InvalidConfig(_) => 1,
+            GenerationError::PlacementExhausted { .. } => 2,
+            GenerationError::PathUnsolvable { .. } => 3,
+            GenerationError::CoordinateOverflow => 4,
+        }
+    }
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenerationError::InvalidConfig(details) => write!(f, "Invalid configuration: {}", details),
+            GenerationError::PlacementExhausted { room } => write!(f, "Cannot find a valid position for room {}", room),
+            GenerationError::PathUnsolvable { from, to } => write!(f, "Cannot find a path from room {} to room {}", from, to),
+            GenerationError::CoordinateOverflow => write!(f, "Dungeon coordinates overflow"),
+        }
+    }
+}
+
+impl Error for GenerationError {}
+
+impl From<ConfigError> for GenerationError {
+    fn from(error: ConfigError) -> Self {
+        GenerationError::InvalidConfig(error.to_string())
+    }
+}
+
+impl From<Vec<ConfigError>> for GenerationError {
+    fn from(errors: Vec<ConfigError>) -> Self {
+        let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+
+        GenerationError::InvalidConfig(details.join(", "))
+    }
+}
@@ -0,0 +1,9 @@
+mod image;
+mod json;
+mod svg;
+mod tiled;
+
+pub use image::{render, Image, ImageTooBig, Palette, RenderOptions, Rgb, MAX_PIXELS};
+pub use json::{to_json, to_json_line};
+pub use svg::{to_svg, SvgOptions};
+pub use tiled::{to_tmj, to_tmx, TileIds, TiledOptions};
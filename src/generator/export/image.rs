@@ -0,0 +1,254 @@
+use crate::generator::layout::DungeonLayout;
+use crate::generator::map::Map;
+use crate::generator::math::Vector;
+use std::{error::Error, fmt};
+
+/// Biggest image `render` draws, 768MB of pixels
+pub const MAX_PIXELS: u64 = 1 << 28;
+
+/// Digits of the room labels, 3x5 pixels, one row per byte from the top, the
+/// 3 lowest bits of each row from left to right
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// RGB colour
+pub type Rgb = [u8; 3];
+
+/// Colour of each kind of tile, and of the decorations of the image
+#[derive(Copy, Clone, Debug)]
+pub struct Palette {
+    pub empty: Rgb,
+    pub floor: Rgb,
+    pub corridor: Rgb,
+    pub door: Rgb,
+    pub wall: Rgb,
+    pub grid: Rgb,
+    pub label: Rgb,
+    pub overlay: Rgb,
+    /// Room graph of the SVG
+    pub graph: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            empty: [24, 24, 28],
+            floor: [196, 180, 148],
+            corridor: [140, 128, 108],
+            door: [166, 94, 46],
+            wall: [72, 68, 64],
+            grid: [48, 46, 44],
+            label: [160, 24, 24],
+            overlay: [48, 112, 200],
+            graph: [60, 160, 80],
+        }
+    }
+}
+
+impl Palette {
+    pub(super) fn tile(&self, byte: u8) -> Rgb {
+        match byte {
+            1 => self.floor,
+            2 => self.corridor,
+            3 => self.door,
+            4 => self.wall,
+            _ => self.empty,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RenderOptions {
+    pub palette: Palette,
+    /// Size of a tile, in pixels
+    pub scale: u32,
+    /// Outline the tiles, with a scale of 2 or more
+    pub grid: bool,
+    /// Write the id of each room in its center
+    pub labels: bool,
+    /// Draw the path of the corridors of the connections
+    pub overlay: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            palette: Palette::default(),
+            scale: 8,
+            grid: false,
+            labels: false,
+            overlay: false,
+        }
+    }
+}
+
+/// The image of the map at the requested scale would have more than
+/// `MAX_PIXELS` pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageTooBig {
+    pub width: u64,
+    pub height: u64,
+}
+
+impl fmt::Display for ImageTooBig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "image of {}x{} pixels is bigger than the limit of {} pixels", self.width, self.height, MAX_PIXELS)
+    }
+}
+
+impl Error for ImageTooBig {}
+
+/// RGB image, 3 bytes per pixel, rows from top to bottom
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 3],
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64, color: Rgb) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+
+        let n = (x as usize + y as usize * self.width as usize) * 3;
+
+        self.pixels[n..n + 3].copy_from_slice(&color);
+    }
+
+    fn fill(&mut self, x: i64, y: i64, width: i64, height: i64, color: Rgb) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.set(px, py, color);
+            }
+        }
+    }
+
+    /// Binary PPM (P6) file
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+
+        ppm.extend_from_slice(&self.pixels);
+        ppm
+    }
+
+    /// PNG file, 8 bits RGB
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        // the buffer has the size announced in the header, encoding in memory
+        // cannot fail
+        let mut writer = encoder.write_header().unwrap();
+
+        writer.write_image_data(&self.pixels).unwrap();
+        writer.finish().unwrap();
+
+        png
+    }
+}
+
+/// Draw the map, one square of `scale` pixels per tile, with the decorations
+/// selected in the options, fails rather than allocating more than
+/// `MAX_PIXELS` pixels
+pub fn render(map: &Map, layout: &DungeonLayout, options: &RenderOptions) -> Result<Image, ImageTooBig> {
+    let scale = options.scale.max(1) as u64;
+    let (width, height) = (map.width as u64 * scale, map.height as u64 * scale);
+
+    match width.checked_mul(height) {
+        Some(pixels) if pixels <= MAX_PIXELS => (),
+        _ => return Err(ImageTooBig { width, height }),
+    }
+
+    // both sides are below MAX_PIXELS
+    let mut image = Image::new(width as u32, height as u32);
+    let scale = scale as i64;
+
+    for (n, byte) in map.to_bytes().into_iter().enumerate() {
+        let (x, y) = ((n % map.width as usize) as i64, (n / map.width as usize) as i64);
+
+        image.fill(x * scale, y * scale, scale, scale, options.palette.tile(byte));
+    }
+
+    if options.grid && scale >= 2 {
+        for y in 0..map.height as i64 {
+            image.fill(0, y * scale, image.width as i64, 1, options.palette.grid);
+        }
+
+        for x in 0..map.width as i64 {
+            image.fill(x * scale, 0, 1, image.height as i64, options.palette.grid);
+        }
+    }
+
+    if options.overlay {
+        let center = |p: &Vector<i32>| (p.x as i64 * scale + scale / 2, p.y as i64 * scale + scale / 2);
+        let width = (scale / 4).max(1);
+
+        for connection in layout.connections.iter() {
+            for pair in connection.waypoints.windows(2) {
+                line(&mut image, center(&pair[0]), center(&pair[1]), width, options.palette.overlay);
+            }
+        }
+    }
+
+    if options.labels {
+        // font pixels, the labels of small rooms may overflow them
+        let size = (scale / 4).max(1);
+
+        for room in layout.rooms.iter() {
+            let text = room.id.to_string();
+            let width = (text.len() as i64 * 4 - 1) * size;
+            let center = room.bounds.center();
+            let x = center.x as i64 * scale + scale / 2 - width / 2;
+            let y = center.y as i64 * scale + scale / 2 - 5 * size / 2;
+
+            for (i, digit) in text.bytes().enumerate() {
+                glyph(&mut image, &DIGITS[(digit - b'0') as usize], x + i as i64 * 4 * size, y, size, options.palette.label);
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+fn line(image: &mut Image, from: (i64, i64), to: (i64, i64), width: i64, color: Rgb) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).max(1);
+
+    for step in 0..=steps {
+        let x = from.0 + (to.0 - from.0) * step / steps;
+        let y = from.1 + (to.1 - from.1) * step / steps;
+
+        image.fill(x - width / 2, y - width / 2, width, width, color);
+    }
+}
+
+fn glyph(image: &mut Image, rows: &[u8; 5], x: i64, y: i64, size: i64, color: Rgb) {
+    for (row, bits) in rows.iter().enumerate() {
+        for column in 0..3 {
+            if bits & (0b100 >> column) != 0 {
+                image.fill(x + column * size, y + row as i64 * size, size, size, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::Config;
+use crate::generator::layout::DungeonLayout;
+use crate::generator::map::{Map, TileKind};
+use crate::generator::math::Vector;
+use serde::Serialize;
+
+/// Everything needed to rebuild or display a dungeon
+#[derive(Serialize)]
+struct Document<'a> {
+    /// Config the dungeon was generated with, including its seed
+    config: &'a Config,
+    map: MapDocument,
+    layout: &'a DungeonLayout,
+}
+
+#[derive(Serialize)]
+struct MapDocument {
+    width: u32,
+    height: u32,
+    /// Translation from the dungeon coordinates to the map coordinates
+    offset: Vector<i32>,
+    legend: Vec<TileKind>,
+    /// One string of glyphs per row, from top to bottom
+    tiles: Vec<String>,
+}
+
+/// Serialise the map, its layout and the config which produced them to a
+/// JSON document
+pub fn to_json(map: &Map, layout: &DungeonLayout, config: &Config) -> String {
+    // only structs, strings & numbers, nothing which can fail to serialise
+    serde_json::to_string_pretty(&document(map, layout, config)).unwrap()
+}
+
+/// Same document as `to_json`, on a single line for JSON-lines files
+pub fn to_json_line(map: &Map, layout: &DungeonLayout, config: &Config) -> String {
+    serde_json::to_string(&document(map, layout, config)).unwrap()
+}
+
+fn document<'a>(map: &Map, layout: &'a DungeonLayout, config: &'a Config) -> Document<'a> {
+    Document {
+        config,
+        map: MapDocument {
+            width: map.width,
+            height: map.height,
+            offset: map.offset().clone(),
+            legend: Map::legend(),
+            tiles: map.to_ascii().lines().filter(|row| !row.is_empty()).map(String::from).collect(),
+        },
+        layout,
+    }
+}
@@ -0,0 +1,148 @@
+use super::image::{Palette, Rgb};
+use crate::generator::layout::DungeonLayout;
+use crate::generator::map::Map;
+use crate::generator::math::Vector;
+use std::fmt::Write;
+
+#[derive(Copy, Clone, Debug)]
+pub struct SvgOptions {
+    pub palette: Palette,
+    /// Size of a tile, in SVG units
+    pub scale: u32,
+    /// Draw the room graph: one node per room, one edge per connection
+    pub graph: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            palette: Palette::default(),
+            scale: 16,
+            graph: false,
+        }
+    }
+}
+
+fn color(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// Draw the dungeon as an SVG document: the tiles, the outline & the id of
+/// the rooms, the corridor of each connection and its doors, and optionally
+/// the room graph. Each group has a class, to be restyled in the `<style>`
+/// element.
+pub fn to_svg(map: &Map, layout: &DungeonLayout, options: &SvgOptions) -> String {
+    let scale = options.scale.max(1) as i64;
+    let palette = &options.palette;
+    let center = |p: &Vector<i32>| (p.x as i64 * scale + scale / 2, p.y as i64 * scale + scale / 2);
+    let mut svg = String::new();
+
+    // writing to a String cannot fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        map.width as i64 * scale, map.height as i64 * scale,
+    );
+    let _ = writeln!(svg, "<style>");
+    let _ = writeln!(svg, ".room {{ fill: none; stroke: {}; stroke-width: {}; }}", color(palette.label), (scale / 8).max(1));
+    let _ = writeln!(svg, ".label {{ fill: {}; font: bold {}px sans-serif; text-anchor: middle; dominant-baseline: central; }}", color(palette.label), scale);
+    let _ = writeln!(svg, ".path {{ fill: none; stroke: {}; stroke-width: {}; }}", color(palette.overlay), (scale / 4).max(1));
+    let _ = writeln!(svg, ".door {{ fill: {}; stroke: {}; }}", color(palette.door), color(palette.label));
+    let _ = writeln!(svg, ".graph {{ fill: {0}; stroke: {0}; stroke-width: {1}; }}", color(palette.graph), (scale / 4).max(1));
+    let _ = writeln!(svg, "</style>");
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="{}"/>"#, color(palette.empty));
+
+    // one rectangle per run of identical tiles
+    let _ = writeln!(svg, r#"<g class="tiles">"#);
+
+    for (y, row) in map.to_bytes().chunks(map.width.max(1) as usize).enumerate() {
+        let mut x = 0;
+
+        for run in row.chunk_by(|a, b| a == b) {
+            if run[0] != 0 {
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    x as i64 * scale, y as i64 * scale, run.len() as i64 * scale, scale, color(palette.tile(run[0])),
+                );
+            }
+
+            x += run.len();
+        }
+    }
+
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(svg, r#"<g class="paths">"#);
+
+    for connection in layout.connections.iter() {
+        let points: Vec<String> = connection.waypoints.iter()
+            .map(center)
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect();
+
+        let _ = writeln!(svg, r#"<polyline class="path" data-connection="{}" points="{}"/>"#, connection.id, points.join(" "));
+    }
+
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(svg, r#"<g class="doors">"#);
+
+    for connection in layout.connections.iter() {
+        for (room, door) in [(connection.from, &connection.from_door), (connection.to, &connection.to_door)] {
+            let (x, y) = center(door);
+
+            let _ = writeln!(
+                svg,
+                r#"<circle class="door" data-room="{}" data-connection="{}" cx="{}" cy="{}" r="{}"/>"#,
+                room, connection.id, x, y, (scale / 3).max(1),
+            );
+        }
+    }
+
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(svg, r#"<g class="rooms">"#);
+
+    for room in layout.rooms.iter() {
+        let (x, y) = (room.bounds.p1.x as i64 * scale, room.bounds.p1.y as i64 * scale);
+        let (cx, cy) = center(&room.bounds.center());
+
+        let _ = writeln!(
+            svg,
+            r#"<rect class="room" data-room="{}" x="{}" y="{}" width="{}" height="{}"/>"#,
+            room.id, x, y, (room.bounds.p2.x - room.bounds.p1.x) as i64 * scale, (room.bounds.p2.y - room.bounds.p1.y) as i64 * scale,
+        );
+        let _ = writeln!(svg, r#"<text class="label" x="{}" y="{}">{}</text>"#, cx, cy, room.id);
+    }
+
+    let _ = writeln!(svg, "</g>");
+
+    if options.graph {
+        let _ = writeln!(svg, r#"<g class="graph">"#);
+
+        for connection in layout.connections.iter() {
+            let ends = layout.room(connection.from).zip(layout.room(connection.to));
+
+            if let Some((from, to)) = ends {
+                let (x1, y1) = center(&from.bounds.center());
+                let (x2, y2) = center(&to.bounds.center());
+
+                let _ = writeln!(
+                    svg,
+                    r#"<line class="graph" data-connection="{}" x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                    connection.id, x1, y1, x2, y2,
+                );
+            }
+        }
+
+        for room in layout.rooms.iter() {
+            let (x, y) = center(&room.bounds.center());
+
+            let _ = writeln!(svg, r#"<circle class="graph" data-room="{}" cx="{}" cy="{}" r="{}"/>"#, room.id, x, y, (scale / 2).max(1));
+        }
+
+        let _ = writeln!(svg, "</g>");
+    }
+
+    let _ = writeln!(svg, "</svg>");
+
+    svg
+}
@@ -0,0 +1,318 @@
+use crate::generator::layout::DungeonLayout;
+use crate::generator::map::Map;
+use crate::generator::math::Vector;
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+const VERSION: &str = "1.10";
+const TILED_VERSION: &str = "1.10.2";
+const TILES_LAYER: u32 = 1;
+const OBJECTS_LAYER: u32 = 2;
+
+/// Tile id written in the tile layer for each kind of tile, 0 is no tile
+#[derive(Copy, Clone, Debug)]
+pub struct TileIds {
+    pub empty: u32,
+    pub floor: u32,
+    pub corridor: u32,
+    pub door: u32,
+    pub wall: u32,
+}
+
+impl Default for TileIds {
+    /// Same values as `Map::to_bytes`
+    fn default() -> Self {
+        TileIds {
+            empty: 0,
+            floor: 1,
+            corridor: 2,
+            door: 3,
+            wall: 4,
+        }
+    }
+}
+
+impl TileIds {
+    fn id(&self, byte: u8) -> u32 {
+        match byte {
+            1 => self.floor,
+            2 => self.corridor,
+            3 => self.door,
+            4 => self.wall,
+            _ => self.empty,
+        }
+    }
+
+    fn max(&self) -> u32 {
+        [self.empty, self.floor, self.corridor, self.door, self.wall].into_iter().max().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledOptions {
+    pub tile_ids: TileIds,
+    /// Size of a tile, in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Path of an external `.tsx` tileset, an empty tileset is embedded when
+    /// `None`
+    pub tileset: Option<String>,
+}
+
+impl Default for TiledOptions {
+    fn default() -> Self {
+        TiledOptions {
+            tile_ids: TileIds::default(),
+            tile_width: 16,
+            tile_height: 16,
+            tileset: None,
+        }
+    }
+}
+
+enum PropertyValue {
+    Int(usize),
+    String(String),
+}
+
+struct Object {
+    name: String,
+    kind: &'static str,
+    /// Position & size in pixels, points have no size
+    position: Vector<u32>,
+    size: Option<Vector<u32>>,
+    properties: Vec<(&'static str, PropertyValue)>,
+}
+
+/// Room rectangles & door points of the layout, the room ids and the ids of
+/// the connections are kept in the custom properties
+fn objects(layout: &DungeonLayout, options: &TiledOptions) -> Vec<Object> {
+    let pixels = |p: &Vector<i32>| Vector { x: p.x as u32 * options.tile_width, y: p.y as u32 * options.tile_height };
+    let mut objects = Vec::new();
+
+    for room in layout.rooms.iter() {
+        let mut properties = vec![
+            ("room", PropertyValue::Int(room.id)),
+            ("shape", PropertyValue::String(json!(room.shape).as_str().unwrap_or_default().to_string())),
+            ("connections", PropertyValue::String(join(layout.room_connections(room.id).map(|c| c.id)))),
+        ];
+
+        if let Some(prefab) = layout.prefabs.iter().find(|p| p.room == room.id) {
+            properties.push(("prefab", PropertyValue::String(prefab.name.clone())));
+        }
+
+        objects.push(Object {
+            name: format!("room {}", room.id),
+            kind: "room",
+            position: pixels(&room.bounds.p1),
+            size: Some(Vector {
+                x: (room.bounds.p2.x - room.bounds.p1.x) as u32 * options.tile_width,
+                y: (room.bounds.p2.y - room.bounds.p1.y) as u32 * options.tile_height,
+            }),
+            properties,
+        });
+    }
+
+    // a door can be shared by several connections of a room
+    let mut doors: Vec<(usize, Vector<i32>)> = layout.connections.iter()
+        .flat_map(|c| [(c.from, c.from_door.clone()), (c.to, c.to_door.clone())])
+        .collect();
+
+    doors.sort();
+    doors.dedup();
+
+    for (room, door) in doors {
+        let connections = layout.connections.iter()
+            .filter(|c| (c.from == room && c.from_door == door) || (c.to == room && c.to_door == door))
+            .map(|c| c.id);
+        let corner = pixels(&door);
+
+        objects.push(Object {
+            name: String::from("door"),
+            kind: "door",
+            position: Vector { x: corner.x + options.tile_width / 2, y: corner.y + options.tile_height / 2 },
+            size: None,
+            properties: vec![
+                ("room", PropertyValue::Int(room)),
+                ("connections", PropertyValue::String(join(connections))),
+            ],
+        });
+    }
+
+    objects
+}
+
+/// Comma separated list of ids
+fn join(ids: impl Iterator<Item = usize>) -> String {
+    ids.map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn tiles(map: &Map, options: &TiledOptions) -> Vec<u32> {
+    map.to_bytes().into_iter().map(|byte| options.tile_ids.id(byte)).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write the map as a Tiled XML map, with a CSV tile layer and an object
+/// layer holding the rooms & the doors of the layout
+pub fn to_tmx(map: &Map, layout: &DungeonLayout, options: &TiledOptions) -> String {
+    let objects = objects(layout, options);
+    let mut tmx = String::new();
+
+    // writing to a String cannot fail
+    let _ = writeln!(tmx, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        tmx,
+        r#"<map version="{}" tiledversion="{}" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="{}" tileheight="{}" infinite="0" nextlayerid="{}" nextobjectid="{}">"#,
+        VERSION, TILED_VERSION, map.width, map.height, options.tile_width, options.tile_height, OBJECTS_LAYER + 1, objects.len() + 1,
+    );
+
+    match &options.tileset {
+        Some(source) => {
+            let _ = writeln!(tmx, r#" <tileset firstgid="1" source="{}"/>"#, escape(source));
+        }
+        None => {
+            let _ = writeln!(
+                tmx,
+                r#" <tileset firstgid="1" name="dungeon" tilewidth="{}" tileheight="{}" tilecount="{}" columns="0"/>"#,
+                options.tile_width, options.tile_height, options.tile_ids.max(),
+            );
+        }
+    }
+
+    let _ = writeln!(tmx, r#" <layer id="{}" name="tiles" width="{}" height="{}">"#, TILES_LAYER, map.width, map.height);
+    let _ = writeln!(tmx, r#"  <data encoding="csv">"#);
+
+    let rows: Vec<String> = tiles(map, options)
+        .chunks(map.width.max(1) as usize)
+        .map(|row| row.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(","))
+        .collect();
+
+    let _ = writeln!(tmx, "{}", rows.join(",\n"));
+    let _ = writeln!(tmx, "  </data>");
+    let _ = writeln!(tmx, " </layer>");
+    let _ = writeln!(tmx, r#" <objectgroup id="{}" name="layout">"#, OBJECTS_LAYER);
+
+    for (id, object) in objects.iter().enumerate() {
+        let size = object.size.as_ref()
+            .map_or(String::new(), |s| format!(r#" width="{}" height="{}""#, s.x, s.y));
+
+        let _ = writeln!(
+            tmx,
+            r#"  <object id="{}" name="{}" type="{}" x="{}" y="{}"{}>"#,
+            id + 1, escape(&object.name), object.kind, object.position.x, object.position.y, size,
+        );
+        let _ = writeln!(tmx, "   <properties>");
+
+        for (name, value) in object.properties.iter() {
+            let _ = match value {
+                PropertyValue::Int(value) => writeln!(tmx, r#"    <property name="{}" type="int" value="{}"/>"#, name, value),
+                PropertyValue::String(value) => writeln!(tmx, r#"    <property name="{}" value="{}"/>"#, name, escape(value)),
+            };
+        }
+
+        let _ = writeln!(tmx, "   </properties>");
+
+        if object.size.is_none() {
+            let _ = writeln!(tmx, "   <point/>");
+        }
+
+        let _ = writeln!(tmx, "  </object>");
+    }
+
+    let _ = writeln!(tmx, " </objectgroup>");
+    let _ = writeln!(tmx, "</map>");
+
+    tmx
+}
+
+/// Write the map as a Tiled JSON map, with the same layers as `to_tmx`
+pub fn to_tmj(map: &Map, layout: &DungeonLayout, options: &TiledOptions) -> String {
+    let objects: Vec<Value> = objects(layout, options).into_iter().enumerate()
+        .map(|(id, object)| {
+            let properties: Vec<Value> = object.properties.iter()
+                .map(|(name, value)| match value {
+                    PropertyValue::Int(value) => json!({ "name": name, "type": "int", "value": value }),
+                    PropertyValue::String(value) => json!({ "name": name, "type": "string", "value": value }),
+                })
+                .collect();
+            let size = object.size.clone().unwrap_or(Vector { x: 0, y: 0 });
+
+            json!({
+                "id": id + 1,
+                "name": object.name,
+                "type": object.kind,
+                "x": object.position.x,
+                "y": object.position.y,
+                "width": size.x,
+                "height": size.y,
+                "point": object.size.is_none(),
+                "rotation": 0,
+                "visible": true,
+                "properties": properties,
+            })
+        })
+        .collect();
+
+    let tileset = match &options.tileset {
+        Some(source) => json!({ "firstgid": 1, "source": source }),
+        None => json!({
+            "firstgid": 1,
+            "name": "dungeon",
+            "tilewidth": options.tile_width,
+            "tileheight": options.tile_height,
+            "tilecount": options.tile_ids.max(),
+            "columns": 0,
+        }),
+    };
+
+    let document = json!({
+        "type": "map",
+        "version": VERSION,
+        "tiledversion": TILED_VERSION,
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "width": map.width,
+        "height": map.height,
+        "tilewidth": options.tile_width,
+        "tileheight": options.tile_height,
+        "infinite": false,
+        "nextlayerid": OBJECTS_LAYER + 1,
+        "nextobjectid": objects.len() + 1,
+        "tilesets": [tileset],
+        "layers": [
+            {
+                "id": TILES_LAYER,
+                "name": "tiles",
+                "type": "tilelayer",
+                "x": 0,
+                "y": 0,
+                "width": map.width,
+                "height": map.height,
+                "opacity": 1,
+                "visible": true,
+                "encoding": "csv",
+                "data": tiles(map, options),
+            },
+            {
+                "id": OBJECTS_LAYER,
+                "name": "layout",
+                "type": "objectgroup",
+                "draworder": "topdown",
+                "x": 0,
+                "y": 0,
+                "opacity": 1,
+                "visible": true,
+                "objects": objects,
+            },
+        ],
+    });
+
+    // only maps, strings & numbers, nothing which can fail to serialise
+    serde_json::to_string_pretty(&document).unwrap()
+}
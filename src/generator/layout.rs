@@ -0,0 +1,69 @@
+use super::math::{Rectangle, Vector};
+use super::shape::RoomShape;
+use serde::Serialize;
+
+/// Rooms & connections of a generated dungeon.
+/// All the positions are expressed in map coordinates, they can be used to
+/// index the tiles of the `Map` generated along with the layout.
+#[derive(Clone, Debug, Serialize)]
+pub struct DungeonLayout {
+    pub rooms: Vec<RoomLayout>,
+    pub connections: Vec<ConnectionLayout>,
+    /// Rooms built from a prefab
+    pub prefabs: Vec<PrefabLayout>,
+    /// Changes made to the connections to keep every room reachable
+    pub repairs: Vec<Repair>,
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Serialize)]
+pub struct RoomLayout {
+    pub id: usize,
+    pub bounds: Rectangle,
+    pub shape: RoomShape,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionLayout {
+    pub id: usize,
+    /// Id of the room the connection starts from
+    pub from: usize,
+    /// Id of the room the connection leads to
+    pub to: usize,
+    /// Door of the `from` room
+    pub from_door: Vector<i32>,
+    /// Door of the `to` room
+    pub to_door: Vector<i32>,
+    /// Corridor waypoints, from `from_door` to `to_door` (both included)
+    pub waypoints: Vec<Vector<i32>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PrefabLayout {
+    /// Id of the room
+    pub room: usize,
+    /// Name of the prefab the room is built from
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Repair {
+    /// The corridor could not be traced at first and has been traced again
+    Rerouted { from: usize, to: usize },
+    /// The corridor could not be traced, the connection has been removed
+    Removed { from: usize, to: usize },
+    /// A connection has been added to reach an isolated room
+    Added { from: usize, to: usize },
+}
+
+impl DungeonLayout {
+    pub fn room(&self, id: usize) -> Option<&RoomLayout> {
+        self.rooms.iter().find(|r| r.id == id)
+    }
+
+    /// List the connections starting from or leading to a room
+    pub fn room_connections(&self, id: usize) -> impl Iterator<Item = &ConnectionLayout> {
+        self.connections.iter().filter(move |c| c.from == id || c.to == id)
+    }
+}
@@ -1,10 +1,16 @@
+use super::distance::{DistanceMap, TileCosts};
+use super::errors::{ConfigError, GenerationError};
+use super::layout::{DungeonLayout, RoomLayout};
 use super::math::{Rectangle, Vector};
+use super::prefab::ANCHOR;
+use super::shape::Shape;
+use serde::{Deserialize, Serialize};
 use std::cmp;
 
 pub struct Map {
-    pub width: u8,
-    pub height: u8,
-    offset: Vector<i8>,
+    pub width: u32,
+    pub height: u32,
+    offset: Vector<i32>,
     grid: Vec<Tile>,
 }
 
@@ -14,6 +20,105 @@ enum Tile {
     Floor,
     Door,
     Corridor,
+    Wall,
+}
+
+impl Tile {
+    /// Tile of a glyph of `Map::to_ascii`, door anchors of the prefabs are
+    /// walls until a corridor reaches them
+    fn from_glyph(glyph: char) -> Tile {
+        match glyph {
+            'x' => Tile::Floor,
+            '#' => Tile::Corridor,
+            'o' => Tile::Door,
+            'w' | ANCHOR => Tile::Wall,
+            _ => Tile::Empty,
+        }
+    }
+
+    fn is_walkable(&self) -> bool {
+        matches!(self, Tile::Floor | Tile::Door | Tile::Corridor)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Tile::Empty => "empty",
+            Tile::Floor => "floor",
+            Tile::Corridor => "corridor",
+            Tile::Door => "door",
+            Tile::Wall => "wall",
+        }
+    }
+
+    fn glyph(&self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::Floor => 'x',
+            Tile::Corridor => '#',
+            Tile::Door => 'o',
+            Tile::Wall => 'w',
+        }
+    }
+
+    fn byte(&self) -> u8 {
+        match self {
+            Tile::Empty => 0,
+            Tile::Floor => 1,
+            Tile::Corridor => 2,
+            Tile::Door => 3,
+            Tile::Wall => 4,
+        }
+    }
+}
+
+/// Name, ASCII glyph & byte of a kind of tile
+#[derive(Clone, Debug, Serialize)]
+pub struct TileKind {
+    pub name: &'static str,
+    pub glyph: char,
+    pub byte: u8,
+}
+
+/// Neighbours taken into account when wrapping walkable tiles with walls,
+/// `Four` leaves the corners of the rooms and the corridor turns open.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Neighbourhood {
+    Four = 4,
+    Eight = 8,
+}
+
+/// Code of the `walls` of a `Config`
+impl TryFrom<u32> for Neighbourhood {
+    type Error = ConfigError;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            4 => Ok(Neighbourhood::Four),
+            8 => Ok(Neighbourhood::Eight),
+            _ => Err(ConfigError::UnknownCode { field: "walls", value: code }),
+        }
+    }
+}
+
+impl From<Neighbourhood> for u32 {
+    fn from(walls: Neighbourhood) -> Self {
+        walls as u32
+    }
+}
+
+impl Neighbourhood {
+    fn offsets(&self) -> &'static [(i32, i32)] {
+        match self {
+            Neighbourhood::Four => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighbourhood::Eight => &[
+                (-1, -1), (0, -1), (1, -1),
+                (-1, 0), (1, 0),
+                (-1, 1), (0, 1), (1, 1),
+            ],
+        }
+    }
 }
 
 impl Map {
@@ -26,19 +131,24 @@ impl Map {
         }
     }
 
-    pub fn size(&self) -> (u8, u8) {
+    pub fn size(&self) -> (u32, u32) {
         (self.width, self.height)
     }
 
+    /// Translation from the dungeon coordinates to the map coordinates
+    pub fn offset(&self) -> &Vector<i32> {
+        &self.offset
+    }
+
+    /// Kinds of tiles found in `to_ascii` & `to_bytes`
+    pub fn legend() -> Vec<TileKind> {
+        [Tile::Empty, Tile::Floor, Tile::Corridor, Tile::Door, Tile::Wall].iter()
+            .map(|tile| TileKind { name: tile.name(), glyph: tile.glyph(), byte: tile.byte() })
+            .collect()
+    }
+
     pub fn to_ascii(&self) -> String {
-        let mut map_string: String = self.grid.clone().into_iter().map(|i| {
-            match i {
-                Tile::Floor => "x",
-                Tile::Corridor => "#",
-                Tile::Door => "o",
-                _ => ".",
-            }
-        }).collect();
+        let mut map_string: String = self.grid.iter().map(|tile| tile.glyph()).collect();
 
         for i in (0..self.grid.len()).step_by(self.width as usize).rev() {
             map_string.insert(i, '\n');
@@ -48,26 +158,21 @@ impl Map {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let map_bytes: Vec<u8> = self.grid.clone().into_iter().map(|i| {
-            match i {
-                Tile::Floor => 1,
-                Tile::Corridor => 2,
-                Tile::Door => 3,
-                _ => 0,
-            }
-        }).collect();
-
-        map_bytes
+        self.grid.iter().map(|tile| tile.byte()).collect()
     }
 
     pub fn clear(&mut self) {
-        self.grid = Map::new_grid(self.width as u32, self.height as u32);
+        self.grid = Map::new_grid(self.width, self.height);
     }
 
-    pub fn resize(&mut self, min: &Vector<i8>, max: &Vector<i8>) {
+    pub fn resize(&mut self, min: &Vector<i32>, max: &Vector<i32>) -> Result<(), GenerationError> {
         let (mut w, mut h) = self.size();
-        let width = (max.x - min.x) as u8;
-        let height = (max.y - min.y) as u8;
+        let extent = |min: i32, max: i32| max.checked_sub(min)
+            .and_then(|e| u32::try_from(e).ok())
+            .and_then(|e| e.checked_add(1))
+            .ok_or(GenerationError::CoordinateOverflow);
+        let width = extent(min.x, max.x)?;
+        let height = extent(min.y, max.y)?;
 
         if width > w {
             w = width;
@@ -77,64 +182,242 @@ impl Map {
             h = height;
         }
 
+        if (w as usize).checked_mul(h as usize).is_none() {
+            return Err(GenerationError::CoordinateOverflow);
+        }
+
         self.offset = Vector {
             x: if min.x < 0 { -min.x } else { 0 },
             y: if min.y < 0 { -min.y } else { 0 },
         };
 
-        self.width = w + 1;
-        self.height = h + 1;
+        self.width = w;
+        self.height = h;
         self.clear();
+
+        Ok(())
     }
 
-    pub fn add_room(&mut self, rect: &Rectangle) {
-        for y in rect.p1.y..rect.p2.y {
-            let p1_x = (rect.p1.x + self.offset.x) as u32;
-            let p2_x = (rect.p2.x + self.offset.x) as u32;
-            let y = (y + self.offset.y) as u32;
+    /// Draw the floor of a room, or the tiles of a prefab. The walls of the
+    /// prefabs don't cut the corridors already drawn across them.
+    pub fn add_room(&mut self, rect: &Rectangle, shape: &Shape) {
+        for p in shape.cells(rect) {
+            let n = self.index(p.x, p.y);
+            let tile = match shape {
+                Shape::Prefab(prefab) => {
+                    let glyph = prefab.glyph(&Vector { x: p.x - rect.p1.x, y: p.y - rect.p1.y });
+
+                    Tile::from_glyph(glyph.unwrap_or('.'))
+                }
+                _ => Tile::Floor,
+            };
 
-            for x in p1_x..p2_x {
-                self.grid[(x + y * self.width as u32) as usize] = Tile::Floor;
+            if tile.is_walkable() || !self.grid[n].is_walkable() {
+                self.grid[n] = tile;
             }
         }
     }
 
-    pub fn add_door(&mut self, position: &Vector<i8>) {
-        let x = (position.x + self.offset.x) as u32;
-        let y = (position.y + self.offset.y) as u32;
+    pub fn add_floor(&mut self, position: &Vector<i32>) {
+        let n = self.index(position.x, position.y);
 
-        self.grid[(x + y * self.width as u32) as usize] = Tile::Door;
+        self.grid[n] = Tile::Floor;
     }
 
-    pub fn add_corridor(&mut self, from: &Vector<i8>, to: &Vector<i8>) {
+    /// Turn a tile into a wall, whatever it was before
+    pub fn add_wall(&mut self, position: &Vector<i32>) {
+        let n = self.index(position.x, position.y);
+
+        self.grid[n] = Tile::Wall;
+    }
+
+    pub fn add_door(&mut self, position: &Vector<i32>) {
+        let n = self.index(position.x, position.y);
+
+        self.grid[n] = Tile::Door;
+    }
+
+    /// Draw a straight corridor, the floors & doors it runs through are kept
+    /// and the walls of the prefabs are crossed
+    pub fn add_corridor(&mut self, from: &Vector<i32>, to: &Vector<i32>) {
         if from.x == to.x {
             let min_y = cmp::min(from.y, to.y);
             let max_y = cmp::max(from.y, to.y);
             for y in min_y..=max_y {
-                let x = (from.x + self.offset.x) as u32;
-                let y = (y + self.offset.y) as u32;
-                let n = (x + y * self.width as u32) as usize;
-                match self.grid[n] {
-                    Tile::Empty => self.grid[n] = Tile::Corridor,
-                    _ => (),
+                let n = self.index(from.x, y);
+                if let Tile::Empty | Tile::Wall = self.grid[n] {
+                    self.grid[n] = Tile::Corridor;
                 }
             }
         } else {
             let min_x = cmp::min(from.x, to.x);
             let max_x = cmp::max(from.x, to.x);
             for x in min_x..=max_x {
-                let x = (x + self.offset.x) as u32;
-                let y = (from.y + self.offset.y) as u32;
-                let n = (x + y * self.width as u32) as usize;
-                match self.grid[n] {
-                    Tile::Empty => self.grid[n] = Tile::Corridor,
-                    _ => (),
+                let n = self.index(x, from.y);
+                if let Tile::Empty | Tile::Wall = self.grid[n] {
+                    self.grid[n] = Tile::Corridor;
+                }
+            }
+        }
+    }
+
+    /// Turn the corridor tiles next to a floor into doors, so the corridors
+    /// running into a room away from the doors of their connection enter it
+    /// through a door rather than through a hole of its walls
+    pub fn add_crossings(&mut self) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut crossings = Vec::new();
+
+        for y in 0..height {
+            for x in 0..width {
+                let n = (x + y * width) as usize;
+
+                if !matches!(self.grid[n], Tile::Corridor) {
+                    continue;
+                }
+
+                let crossing = Neighbourhood::Four.offsets().iter().any(|(dx, dy)| {
+                    let (nx, ny) = (x + dx, y + dy);
+
+                    nx >= 0 && nx < width && ny >= 0 && ny < height
+                        && matches!(self.grid[(nx + ny * width) as usize], Tile::Floor)
+                });
+
+                if crossing {
+                    crossings.push(n);
                 }
             }
         }
+
+        for n in crossings {
+            self.grid[n] = Tile::Door;
+        }
+    }
+
+    /// Turn every empty tile next to a floor, a door or a corridor into a wall.
+    /// Corridors entering a room replace its walls, so doors are always
+    /// reachable from the outside.
+    pub fn add_walls(&mut self, neighbourhood: Neighbourhood) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut walls = Vec::new();
+
+        for y in 0..height {
+            for x in 0..width {
+                let n = (x + y * width) as usize;
+
+                if !matches!(self.grid[n], Tile::Empty) {
+                    continue;
+                }
+
+                let wrapped = neighbourhood.offsets().iter().any(|(dx, dy)| {
+                    let (nx, ny) = (x + dx, y + dy);
+
+                    nx >= 0 && nx < width && ny >= 0 && ny < height
+                        && self.grid[(nx + ny * width) as usize].is_walkable()
+                });
+
+                if wrapped {
+                    walls.push(n);
+                }
+            }
+        }
+
+        for n in walls {
+            self.grid[n] = Tile::Wall;
+        }
+    }
+
+    /// List the floor, door & corridor tiles which cannot be walked to from
+    /// `start`. Positions are in map coordinates.
+    pub fn unreachable_tiles(&self, start: &Vector<i32>) -> Vec<Vector<i32>> {
+        let reachable = self.reachable_from(start);
+
+        self.grid.iter().enumerate()
+            .filter(|(n, tile)| tile.is_walkable() && !reachable[*n])
+            .map(|(n, _)| Vector { x: (n % self.width as usize) as i32, y: (n / self.width as usize) as i32 })
+            .collect()
+    }
+
+    /// Walking distance from the nearest of the `sources` to every tile, a
+    /// step onto a floor, corridor or door tile costing 1. Positions are in
+    /// map coordinates.
+    pub fn distance_map(&self, sources: &[Vector<i32>]) -> DistanceMap {
+        self.distance_map_with_costs(sources, &TileCosts::default())
+    }
+
+    /// `distance_map` with the cost of a step onto each kind of walkable tile
+    pub fn distance_map_with_costs(&self, sources: &[Vector<i32>], costs: &TileCosts) -> DistanceMap {
+        let costs: Vec<Option<u32>> = self.grid.iter()
+            .map(|tile| match tile {
+                Tile::Floor => Some(costs.floor),
+                Tile::Corridor => Some(costs.corridor),
+                Tile::Door => Some(costs.door),
+                _ => None,
+            })
+            .collect();
+
+        DistanceMap::new(self.width, self.height, &costs, sources)
+    }
+
+    /// Walkable tiles within the bounds of a room of the layout
+    pub fn room_tiles(&self, room: &RoomLayout) -> Vec<Vector<i32>> {
+        let bounds = &room.bounds;
+
+        (bounds.p1.y.max(0)..bounds.p2.y.min(self.height as i32))
+            .flat_map(|y| (bounds.p1.x.max(0)..bounds.p2.x.min(self.width as i32)).map(move |x| Vector { x, y }))
+            .filter(|p| self.grid[p.x as usize + p.y as usize * self.width as usize].is_walkable())
+            .collect()
+    }
+
+    /// Id of the room the farthest from the room `start` with its walking
+    /// distance, `start` itself when no other room can be reached. `None` if
+    /// there is no such room in the layout.
+    pub fn farthest_room(&self, layout: &DungeonLayout, start: usize) -> Option<(usize, u32)> {
+        let room = layout.room(start)?;
+
+        self.distance_map(&self.room_tiles(room)).farthest_room(layout)
+    }
+
+    /// Flood fill the walkable tiles from `start`, in map coordinates
+    fn reachable_from(&self, start: &Vector<i32>) -> Vec<bool> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut reachable = vec![false; self.grid.len()];
+        let mut stack = Vec::new();
+
+        if start.x >= 0 && start.x < width && start.y >= 0 && start.y < height {
+            stack.push((start.x, start.y));
+        }
+
+        while let Some((x, y)) = stack.pop() {
+            let n = (x + y * width) as usize;
+
+            if reachable[n] || !self.grid[n].is_walkable() {
+                continue;
+            }
+
+            reachable[n] = true;
+
+            for (dx, dy) in Neighbourhood::Four.offsets() {
+                let (nx, ny) = (x + dx, y + dy);
+
+                if nx >= 0 && nx < width && ny >= 0 && ny < height {
+                    stack.push((nx, ny));
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Convert dungeon coordinates into an index of the grid
+    fn index(&self, x: i32, y: i32) -> usize {
+        let x = (x + self.offset.x) as usize;
+        let y = (y + self.offset.y) as usize;
+
+        x + y * self.width as usize
     }
 
     fn new_grid(width: u32, height: u32) -> Vec<Tile> {
-        vec![Tile::Empty; (width * height) as usize]
+        vec![Tile::Empty; width as usize * height as usize]
     }
 }
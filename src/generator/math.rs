@@ -3,9 +3,11 @@ extern crate nalgebra as na;
 use std::cmp;
 use std::ops::Add;
 use na::Point2;
+use serde::{Deserialize, Serialize};
 
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Vector<T> {
     pub x: T,
     pub y: T,
@@ -22,10 +24,31 @@ impl<T: Add<Output = T>> Add for Vector<T> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    /// Unit vector pointing toward the direction, North being Y-
+    pub fn step(&self) -> Vector<i32> {
+        match self {
+            Direction::North => Vector { x: 0, y: -1 },
+            Direction::South => Vector { x: 0, y: 1 },
+            Direction::East => Vector { x: 1, y: 0 },
+            Direction::West => Vector { x: -1, y: 0 },
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Rectangle {
-    pub p1: Vector<i8>,
-    pub p2: Vector<i8>,
+    pub p1: Vector<i32>,
+    pub p2: Vector<i32>,
 }
 
 impl Rectangle {
@@ -36,17 +59,24 @@ impl Rectangle {
         width_check && height_check
     }
 
-    pub fn is_inside(&self, p: Vector<i8>) -> bool {
+    pub fn is_inside(&self, p: Vector<i32>) -> bool {
         p.x >= self.p1.x
             && p.x < self.p2.x
             && p.y >= self.p1.y
             && p.y < self.p2.y
     }
 
-    pub fn size(&self) -> Vector<u8> {
+    pub fn center(&self) -> Vector<i32> {
+        Vector {
+            x: (self.p1.x + self.p2.x) / 2,
+            y: (self.p1.y + self.p2.y) / 2,
+        }
+    }
+
+    pub fn size(&self) -> Vector<u32> {
         Vector {
-            x: (self.p2.x - self.p1.x) as u8,
-            y: (self.p2.y - self.p1.y) as u8,
+            x: (self.p2.x - self.p1.x) as u32,
+            y: (self.p2.y - self.p1.y) as u32,
         }
     }
 
@@ -0,0 +1,167 @@
+use super::math::Vector;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+
+/// Choose the pairs of rooms to connect: the minimum spanning tree of the
+/// room centres, plus `loop_factor` percent of the other edges linking each
+/// room to its `neighbours` nearest rooms, to make loops.
+///
+/// Pairs are `(a, b)` with `a > b`, the tree edges first.
+pub fn plan(centers: &[Vector<i32>], neighbours: u32, loop_factor: u32, rng: &mut ChaCha8Rng) -> Vec<(usize, usize)> {
+    let mut edges = spanning_tree(centers);
+    let tree: HashSet<(usize, usize)> = edges.iter().copied().collect();
+    let mut extra: Vec<(usize, usize)> = nearest_neighbours(centers, neighbours as usize)
+        .into_iter()
+        .filter(|edge| !tree.contains(edge))
+        .collect();
+
+    let count = (extra.len() * loop_factor.min(100) as usize + 50) / 100;
+
+    extra.shuffle(rng);
+    extra.truncate(count);
+    extra.sort_unstable_by_key(|(a, b)| (distance(&centers[*a], &centers[*b]), *a, *b));
+    edges.extend(extra);
+
+    edges
+}
+
+fn distance(a: &Vector<i32>, b: &Vector<i32>) -> i64 {
+    let (dx, dy) = ((a.x - b.x) as i64, (a.y - b.y) as i64);
+
+    dx * dx + dy * dy
+}
+
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    (a.max(b), a.min(b))
+}
+
+/// Prim's algorithm on the complete graph of the centres, in the order the
+/// rooms join the tree
+fn spanning_tree(centers: &[Vector<i32>]) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+
+    if centers.is_empty() {
+        return edges;
+    }
+
+    let mut in_tree = vec![false; centers.len()];
+    // closest room of the tree, and its distance, for each room out of it
+    let mut closest: Vec<(i64, usize)> = centers.iter().map(|c| (distance(c, &centers[0]), 0)).collect();
+
+    in_tree[0] = true;
+
+    for _ in 1..centers.len() {
+        let next = (0..centers.len())
+            .filter(|i| !in_tree[*i])
+            .min_by_key(|i| (closest[*i].0, *i))
+            .unwrap();
+
+        in_tree[next] = true;
+        edges.push(edge(next, closest[next].1));
+
+        for i in 0..centers.len() {
+            let d = distance(&centers[i], &centers[next]);
+
+            if !in_tree[i] && d < closest[i].0 {
+                closest[i] = (d, next);
+            }
+        }
+    }
+
+    edges
+}
+
+/// Edges from each room to its `k` nearest rooms, without duplicates
+fn nearest_neighbours(centers: &[Vector<i32>], k: usize) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+
+    for (i, center) in centers.iter().enumerate() {
+        let mut others: Vec<(i64, usize)> = centers.iter().enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(j, other)| (distance(center, other), j))
+            .collect();
+
+        // only the k nearest rooms need to be sorted
+        if k < others.len() {
+            others.select_nth_unstable(k);
+            others.truncate(k);
+        }
+
+        others.sort_unstable();
+        edges.extend(others.into_iter().take(k).map(|(_, j)| edge(i, j)));
+    }
+
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::random;
+    use rand::SeedableRng;
+
+    fn centers(seed: u64) -> Vec<Vector<i32>> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        (0..20).map(|_| Vector { x: random::gen_range(&mut rng, 0..100), y: random::gen_range(&mut rng, 0..100) }).collect()
+    }
+
+    /// Weight of the minimum spanning tree, with Kruskal's algorithm
+    fn kruskal(centers: &[Vector<i32>]) -> i64 {
+        let mut groups: Vec<usize> = (0..centers.len()).collect();
+        let mut edges: Vec<(usize, usize)> = (0..centers.len()).flat_map(|a| (0..a).map(move |b| (a, b))).collect();
+        let mut weight = 0;
+
+        edges.sort_by_key(|(a, b)| distance(&centers[*a], &centers[*b]));
+
+        for (a, b) in edges {
+            let (ga, gb) = (groups[a], groups[b]);
+
+            if ga != gb {
+                weight += distance(&centers[a], &centers[b]);
+                groups.iter_mut().filter(|g| **g == gb).for_each(|g| *g = ga);
+            }
+        }
+
+        weight
+    }
+
+    #[test]
+    fn the_tree_spans_every_room_at_the_lowest_cost() {
+        for seed in 0..20 {
+            let centers = centers(seed);
+            let tree = spanning_tree(&centers);
+            let mut reached = vec![false; centers.len()];
+
+            reached[0] = true;
+
+            // the rooms join the tree one by one
+            for (a, b) in tree.iter().copied() {
+                assert!(a > b);
+                assert!(reached[a] != reached[b], "Seed {}: ({}, {}) makes a loop", seed, a, b);
+                reached[a] = true;
+                reached[b] = true;
+            }
+
+            assert_eq!(tree.len(), centers.len() - 1);
+            assert_eq!(tree.iter().map(|(a, b)| distance(&centers[*a], &centers[*b])).sum::<i64>(), kruskal(&centers));
+        }
+    }
+
+    #[test]
+    fn the_loop_factor_sets_the_share_of_extra_edges() {
+        let centers = centers(0);
+        let tree = spanning_tree(&centers);
+        let extra = nearest_neighbours(&centers, 3).into_iter().filter(|e| !tree.contains(e)).count();
+
+        for (loop_factor, count) in [(0, 0), (50, extra.div_ceil(2)), (100, extra), (150, extra)] {
+            let edges = plan(&centers, 3, loop_factor, &mut ChaCha8Rng::seed_from_u64(0));
+
+            assert_eq!(edges[..tree.len()], tree[..]);
+            assert_eq!(edges.len(), tree.len() + count, "Loop factor {}", loop_factor);
+        }
+    }
+}
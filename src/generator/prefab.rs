@@ -0,0 +1,205 @@
+use super::errors::GenerationError;
+use super::math::{Direction, Vector};
+use super::random;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Glyph of the door anchors in the templates
+pub const ANCHOR: char = 'D';
+/// Weight of a procedural room, prefab weights are relative to it
+pub const ROOM_WEIGHT: u32 = 100;
+
+const GLYPHS: [char; 6] = ['.', 'x', '#', 'o', 'w', ANCHOR];
+
+/// Hand-authored room, placed as is in the dungeon.
+///
+/// Templates use the glyphs of `Map::to_ascii`, plus `D` for the door anchors
+/// corridors connect to. Anchors are on the edge of the template, on even
+/// tiles. The template can be preceded by `name`, `weight`, `min` & `max`
+/// lines, e.g.
+///
+/// ```text
+/// name: shrine
+/// weight: 10
+/// max: 1
+///
+/// wwwDwww
+/// wxxxxxw
+/// Dxxxxxw
+/// wxxxxxw
+/// wwwwwww
+/// ```
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    /// Chances to pick the prefab instead of a procedural room, whose weight
+    /// is `ROOM_WEIGHT`
+    pub weight: u32,
+    /// Number of copies always placed in the dungeon
+    pub min: u32,
+    /// Maximum number of copies placed in the dungeon
+    pub max: u32,
+    rows: Vec<Vec<char>>,
+    anchors: Vec<(Vector<i32>, Direction)>,
+}
+
+impl Prefab {
+    pub fn parse(text: &str) -> Result<Prefab, GenerationError> {
+        let invalid = |details: String| GenerationError::InvalidConfig(details);
+        let mut prefab = Prefab {
+            name: String::from("prefab"),
+            weight: 10,
+            min: 0,
+            max: 1,
+            rows: Vec::new(),
+            anchors: Vec::new(),
+        };
+
+        for line in text.lines().map(|l| l.trim_end()).filter(|l| !l.is_empty()) {
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                let number = || value.parse::<u32>().map_err(|_| invalid(format!("Invalid prefab {}: {}", key, value)));
+
+                match key.trim() {
+                    "name" => prefab.name = value.to_string(),
+                    "weight" => prefab.weight = number()?,
+                    "min" => prefab.min = number()?,
+                    "max" => prefab.max = number()?,
+                    key => return Err(invalid(format!("Unknown prefab property: {}", key))),
+                }
+            } else {
+                prefab.rows.push(line.chars().collect());
+            }
+        }
+
+        let name = prefab.name.clone();
+        let (width, height) = (prefab.rows.first().map_or(0, |r| r.len()), prefab.rows.len());
+
+        if width < 4 || height < 4 {
+            return Err(invalid(format!("Prefab {} must be at least 4x4", name)));
+        }
+
+        if prefab.rows.iter().any(|r| r.len() != width) {
+            return Err(invalid(format!("Rows of prefab {} must have the same length", name)));
+        }
+
+        if let Some(c) = prefab.rows.iter().flatten().find(|c| !GLYPHS.contains(c)) {
+            return Err(invalid(format!("Unknown glyph in prefab {}: {}", name, c)));
+        }
+
+        if prefab.min > prefab.max {
+            return Err(invalid(format!("Min count of prefab {} cannot be bigger than max count", name)));
+        }
+
+        for (y, row) in prefab.rows.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, c)| **c == ANCHOR) {
+                let (x, y) = (x as i32, y as i32);
+                let direction = match (x, y) {
+                    (_, 0) if x > 0 && x < width as i32 - 1 => Direction::North,
+                    (_, y) if y == height as i32 - 1 && x > 0 && x < width as i32 - 1 => Direction::South,
+                    (0, _) if y > 0 && y < height as i32 - 1 => Direction::West,
+                    (x, _) if x == width as i32 - 1 && y > 0 && y < height as i32 - 1 => Direction::East,
+                    _ => return Err(invalid(format!("Anchor ({}, {}) of prefab {} must be on an edge, not a corner", x, y, name))),
+                };
+
+                // corridors are aligned on even cells
+                if x % 2 != 0 || y % 2 != 0 {
+                    return Err(invalid(format!("Anchor ({}, {}) of prefab {} must be on an even tile", x, y, name)));
+                }
+
+                prefab.anchors.push((Vector { x, y }, direction));
+            }
+        }
+
+        if prefab.anchors.is_empty() {
+            return Err(invalid(format!("Prefab {} has no door anchor", name)));
+        }
+
+        Ok(prefab)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Prefab, GenerationError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| GenerationError::InvalidConfig(format!("Cannot read prefab {}: {}", path.display(), e)))?;
+
+        Prefab::parse(&text)
+    }
+
+    pub fn size(&self) -> Vector<i32> {
+        Vector {
+            x: self.rows.first().map_or(0, |r| r.len()) as i32,
+            y: self.rows.len() as i32,
+        }
+    }
+
+    /// Glyph of the template at a position relative to its top left corner
+    pub fn glyph(&self, p: &Vector<i32>) -> Option<char> {
+        if p.x < 0 || p.y < 0 {
+            return None;
+        }
+
+        self.rows.get(p.y as usize).and_then(|r| r.get(p.x as usize)).copied()
+    }
+
+    /// Door anchors, relative to the top left corner of the template, and the
+    /// side they open to
+    pub fn anchors(&self) -> &[(Vector<i32>, Direction)] {
+        &self.anchors
+    }
+}
+
+/// Choose the prefabs to place among `rooms_count` rooms: the minimum count of
+/// each prefab, then for each remaining room either a procedural room or a
+/// prefab under its maximum count. Returns the index of the prefab of each
+/// room, the random generator is left untouched without prefabs.
+pub fn plan(prefabs: &[Arc<Prefab>], rooms_count: usize, rng: &mut ChaCha8Rng) -> Result<Vec<Option<usize>>, GenerationError> {
+    let mut rooms: Vec<Option<usize>> = Vec::new();
+
+    if prefabs.is_empty() {
+        return Ok(vec![None; rooms_count]);
+    }
+
+    for (index, prefab) in prefabs.iter().enumerate() {
+        rooms.extend((0..prefab.min).map(|_| Some(index)));
+    }
+
+    if rooms.len() > rooms_count {
+        return Err(GenerationError::InvalidConfig("Prefab min counts exceed the number of rooms".to_string()));
+    }
+
+    let mut counts: Vec<u32> = prefabs.iter().map(|p| p.min).collect();
+
+    while rooms.len() < rooms_count {
+        let candidates: Vec<(Option<usize>, u32)> = std::iter::once((None, ROOM_WEIGHT))
+            .chain(prefabs.iter().enumerate()
+                .filter(|(i, p)| counts[*i] < p.max && p.weight > 0)
+                .map(|(i, p)| (Some(i), p.weight)))
+            .collect();
+        let total: i64 = candidates.iter().map(|(_, w)| *w as i64).sum();
+        let mut value = random::gen_range(rng, 0..total);
+        let mut choice = None;
+
+        for (candidate, weight) in candidates {
+            if value < weight as i64 {
+                choice = candidate;
+                break;
+            }
+
+            value -= weight as i64;
+        }
+
+        if let Some(index) = choice {
+            counts[index] += 1;
+        }
+
+        rooms.push(choice);
+    }
+
+    rooms.shuffle(rng);
+
+    Ok(rooms)
+}
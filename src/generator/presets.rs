@@ -0,0 +1,68 @@
+use super::map::Neighbourhood;
+use super::math::Vector;
+use super::router::Router;
+use super::shape::ShapeWeights;
+use super::strategy::{CaveRules, Pockets, Strategy};
+use crate::{Config, MinMax};
+
+/// Names of the built-in presets, see `Config::preset`
+pub const PRESETS: [&str; 4] = ["tight-crypt", "sprawling-caves", "arena", "labyrinth"];
+
+/// Config of a built-in preset, `None` if the name is unknown
+pub fn preset(name: &str) -> Option<Config> {
+    let config = match name {
+        // many small rooms packed together, straight corridors & few loops
+        "tight-crypt" => Config {
+            rooms_count: 18,
+            rooms_min_size: Vector { x: 4, y: 4 },
+            rooms_max_size: Vector { x: 5, y: 5 },
+            rooms_spacing: MinMax { min: 3, max: 4 },
+            path_extension: MinMax { min: 2, max: 3 },
+            router: Router::AStar.into(),
+            turn_penalty: 8,
+            loop_factor: 5,
+            ..Config::default()
+        },
+        // wide caverns joined by tunnels
+        "sprawling-caves" => Config {
+            strategy: Strategy::Cave.into(),
+            bounds: Vector { x: 96, y: 64 },
+            cave: CaveRules {
+                fill: 46,
+                birth: 5,
+                survival: 4,
+                iterations: 5,
+                min_pocket: 12,
+                pockets: Pockets::Join.into(),
+            },
+            ..Config::default()
+        },
+        // a few big halls evenly spread, with pillars and many loops
+        "arena" => Config {
+            rooms_count: 6,
+            rooms_min_size: Vector { x: 9, y: 9 },
+            rooms_max_size: Vector { x: 13, y: 13 },
+            rooms_spacing: MinMax { min: 4, max: 6 },
+            strategy: Strategy::Bsp.into(),
+            bounds: Vector { x: 64, y: 48 },
+            shapes: ShapeWeights { rectangle: 2, ellipse: 1, pillars: 2, ..ShapeWeights::default() },
+            loop_factor: 50,
+            ..Config::default()
+        },
+        // small rooms far apart, long winding corridors crossing each other
+        "labyrinth" => Config {
+            rooms_count: 24,
+            rooms_min_size: Vector { x: 4, y: 4 },
+            rooms_max_size: Vector { x: 6, y: 6 },
+            rooms_spacing: MinMax { min: 6, max: 10 },
+            path_extension: MinMax { min: 2, max: 6 },
+            walls: Neighbourhood::Four.into(),
+            neighbours: 4,
+            loop_factor: 60,
+            ..Config::default()
+        },
+        _ => return None,
+    };
+
+    Some(config)
+}
@@ -0,0 +1,127 @@
+use rand::Rng;
+use std::ops::{Range, RangeInclusive};
+
+/// Sample an integer in the range `[low, high)`
+pub fn gen_range<R, T>(rng: &mut R, range: Range<T>) -> T
+where
+    R: Rng,
+    T: Copy + Into<i64> + TryFrom<i64>,
+{
+    let low = range.start.into();
+    let high = range.end.into();
+
+    assert!(low < high, "gen_range called with `low >= high`");
+
+    from_i64(sample(rng, low, high - 1))
+}
+
+/// Sample an integer in the range `[low, high]`
+pub fn gen_range_inclusive<R, T>(rng: &mut R, range: RangeInclusive<T>) -> T
+where
+    R: Rng,
+    T: Copy + Into<i64> + TryFrom<i64>,
+{
+    let low = (*range.start()).into();
+    let high = (*range.end()).into();
+
+    assert!(low <= high, "gen_range_inclusive called with `low > high`");
+
+    from_i64(sample(rng, low, high))
+}
+
+/// Uniform sampling reproducing the rejection zone `rand` uses for 8 and 16
+/// bits integers. The generator used to work with `u8` & `i8` values, sampling
+/// this way keeps the dungeons of existing seeds unchanged with wider types.
+fn sample<R: Rng>(rng: &mut R, low: i64, high: i64) -> i64 {
+    let range = (high - low + 1) as u64;
+
+    if range > u32::MAX as u64 {
+        return rng.gen_range(low..=high);
+    }
+
+    let range = range as u32;
+
+    // every value of a 8 or 16 bits integer, `rand` keeps the low bits of a
+    // sample instead, wrapped around zero for the signed integers
+    if range == 1 << 8 || range == 1 << 16 {
+        let bits = (rng.gen::<u32>() & (range - 1)) as i64;
+        let half = (range / 2) as i64;
+
+        if low == -half && bits >= half {
+            return bits - range as i64;
+        }
+
+        return if low == -half { bits } else { low + bits };
+    }
+
+    let zone = u32::MAX - (u32::MAX - range + 1) % range;
+
+    loop {
+        let v: u32 = rng.gen();
+        let m = v as u64 * range as u64;
+
+        if m as u32 <= zone {
+            return low + (m >> 32) as i64;
+        }
+    }
+}
+
+fn from_i64<T: TryFrom<i64>>(value: i64) -> T {
+    match T::try_from(value) {
+        Ok(value) => value,
+        Err(_) => unreachable!("sampled value is always within the range bounds"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    /// Ranges of every width a `u8` holds, most of them not powers of two
+    fn ranges() -> impl Iterator<Item = (u8, u8)> {
+        (0..=255u8).step_by(17).flat_map(|low| (low..=255).map(move |high| (low, high)))
+    }
+
+    #[test]
+    fn u8_ranges_sample_as_rand_does() {
+        for (seed, (low, high)) in ranges().enumerate() {
+            let mut ours = ChaCha8Rng::seed_from_u64(seed as u64);
+            let mut theirs = ours.clone();
+
+            for _ in 0..16 {
+                assert_eq!(gen_range_inclusive(&mut ours, low..=high), theirs.gen_range(low..=high), "Range {}..={}", low, high);
+
+                if low < high {
+                    assert_eq!(gen_range(&mut ours, low..high), theirs.gen_range(low..high), "Range {}..{}", low, high);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn signed_ranges_sample_as_rand_does() {
+        let mut ours = ChaCha8Rng::seed_from_u64(7);
+        let mut theirs = ours.clone();
+
+        for _ in 0..64 {
+            assert_eq!(gen_range_inclusive(&mut ours, i16::MIN..=i16::MAX), theirs.gen_range(i16::MIN..=i16::MAX));
+            assert_eq!(gen_range_inclusive(&mut ours, 0..=u16::MAX), theirs.gen_range(0..=u16::MAX));
+        }
+
+        // as wide as a `i8` without being one
+        let mut other = ChaCha8Rng::seed_from_u64(7);
+
+        for _ in 0..64 {
+            assert!((-5..=250).contains(&gen_range_inclusive(&mut other, -5i32..=250)));
+        }
+
+        for (low, high) in [(-128i8, 127i8), (-5, 5), (-100, -3), (-1, 0), (3, 9)] {
+            for _ in 0..64 {
+                assert_eq!(gen_range_inclusive(&mut ours, low..=high), theirs.gen_range(low..=high), "Range {}..={}", low, high);
+                assert_eq!(gen_range(&mut ours, low..high), theirs.gen_range(low..high), "Range {}..{}", low, high);
+            }
+        }
+    }
+}
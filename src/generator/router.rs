@@ -0,0 +1,76 @@
+mod astar;
+mod rotation;
+
+use super::errors::ConfigError;
+use super::math::{Direction, Rectangle, Vector};
+use super::spatial::SpatialIndex;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+pub use astar::AStarRouter;
+pub use rotation::RotationRouter;
+
+/// Corridor solver selected in the `Config`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Router {
+    /// Extend the corridor from one room and turn toward the other one
+    Rotation,
+    /// A* search on the even cells of the grid, around the rooms
+    AStar,
+}
+
+/// Everything a router needs to know to trace a corridor between two doors
+pub struct Route<'a> {
+    /// Door of the first room
+    pub from: Vector<i32>,
+    /// Side of the first room the door opens to
+    pub from_direction: Direction,
+    pub from_room: &'a Rectangle,
+    /// Door of the second room
+    pub to: Vector<i32>,
+    /// Side of the second room the door opens to
+    pub to_direction: Direction,
+    pub to_room: &'a Rectangle,
+    /// Every room of the dungeon, including the two connected ones
+    pub rooms: &'a [Rectangle],
+    /// The same rooms, indexed by position
+    pub index: &'a SpatialIndex,
+}
+
+pub trait CorridorRouter {
+    /// Find the waypoints of a corridor going from `route.from` to `route.to`.
+    /// The doors are not part of the returned waypoints, two consecutive
+    /// points of the corridor (doors included) must be aligned on one axis.
+    /// Returns `None` if no corridor can be found.
+    fn route(&mut self, route: &Route, rng: &mut ChaCha8Rng) -> Option<Vec<Vector<i32>>>;
+}
+
+/// Code of the `router` of a `Config`
+impl TryFrom<u32> for Router {
+    type Error = ConfigError;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            0 => Ok(Router::Rotation),
+            1 => Ok(Router::AStar),
+            _ => Err(ConfigError::UnknownCode { field: "router", value: code }),
+        }
+    }
+}
+
+impl From<Router> for u32 {
+    fn from(router: Router) -> Self {
+        router as u32
+    }
+}
+
+impl Router {
+    pub fn build(&self, path_extension: (u32, u32), turn_penalty: u32) -> Box<dyn CorridorRouter> {
+        match self {
+            Router::Rotation => Box::new(RotationRouter { path_extension }),
+            Router::AStar => Box::new(AStarRouter::new(turn_penalty)),
+        }
+    }
+}
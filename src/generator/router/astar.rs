@@ -0,0 +1,138 @@
+use crate::generator::math::{Direction, Vector};
+use super::{CorridorRouter, Route};
+use rand_chacha::ChaCha8Rng;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Cost of a step from one even cell to the next one
+const STEP_COST: u32 = 2;
+/// Cost of a step along a corridor traced for a previous connection
+const REUSE_COST: u32 = 1;
+/// Free space around the rooms where corridors can go round them
+const MARGIN: i32 = 4;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+
+type Node = (Vector<i32>, Direction);
+
+/// Shortest corridor search on the even cells of the grid.
+/// Corridors never go through a room, except at its doors, and prefer to
+/// follow the corridors already traced by the router.
+pub struct AStarRouter {
+    /// Extra cost of a change of direction, higher values give straighter
+    /// corridors at the price of longer detours
+    pub turn_penalty: u32,
+    corridors: HashSet<Vector<i32>>,
+}
+
+impl AStarRouter {
+    pub fn new(turn_penalty: u32) -> AStarRouter {
+        AStarRouter {
+            turn_penalty,
+            corridors: HashSet::new(),
+        }
+    }
+
+    fn step_cost(&self, position: &Vector<i32>, direction: Direction, next_direction: Direction) -> u32 {
+        let cost = if self.corridors.contains(position) { REUSE_COST } else { STEP_COST };
+
+        if direction == next_direction { cost } else { cost + self.turn_penalty }
+    }
+}
+
+impl CorridorRouter for AStarRouter {
+    fn route(&mut self, route: &Route, _rng: &mut ChaCha8Rng) -> Option<Vec<Vector<i32>>> {
+        let (min, max) = bounds(route);
+        let is_blocked = |p: &Vector<i32>| {
+            *p != route.to
+                && (p.x < min.x || p.y < min.y || p.x > max.x || p.y > max.y
+                    || route.index.contains(p))
+        };
+        let heuristic = |p: &Vector<i32>| ((p.x - route.to.x).abs() + (p.y - route.to.y).abs()) as u32 / STEP_COST * REUSE_COST;
+
+        let start = (route.from.clone(), route.from_direction);
+        let mut costs: HashMap<Node, u32> = HashMap::from([(start.clone(), 0)]);
+        let mut parents: HashMap<Node, Node> = HashMap::new();
+        let mut open = BinaryHeap::from([Reverse((heuristic(&route.from), 0, start.0, start.1))]);
+
+        while let Some(Reverse((_, cost, position, direction))) = open.pop() {
+            if position == route.to {
+                let nodes = backtrack(&parents, (position, direction));
+
+                self.corridors.extend(nodes.iter().cloned());
+
+                return Some(corners(&nodes));
+            }
+
+            if costs.get(&(position.clone(), direction)).is_some_and(|c| cost > *c) {
+                continue;
+            }
+
+            for next_direction in DIRECTIONS {
+                let step = next_direction.step();
+                let next = Vector { x: position.x + step.x * 2, y: position.y + step.y * 2 };
+
+                if is_blocked(&next) {
+                    continue;
+                }
+
+                let next_cost = cost + self.step_cost(&next, direction, next_direction);
+                let node = (next.clone(), next_direction);
+
+                if costs.get(&node).is_none_or(|c| next_cost < *c) {
+                    costs.insert(node.clone(), next_cost);
+                    parents.insert(node, (position.clone(), direction));
+                    open.push(Reverse((next_cost + heuristic(&next), next_cost, next, next_direction)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Search area: the rooms, the doors and a margin around them, aligned on
+/// even cells
+fn bounds(route: &Route) -> (Vector<i32>, Vector<i32>) {
+    let mut min = Vector { x: route.from.x.min(route.to.x), y: route.from.y.min(route.to.y) };
+    let mut max = Vector { x: route.from.x.max(route.to.x), y: route.from.y.max(route.to.y) };
+
+    if let Some(rect) = route.index.bounds() {
+        min = Vector { x: min.x.min(rect.p1.x), y: min.y.min(rect.p1.y) };
+        max = Vector { x: max.x.max(rect.p2.x), y: max.y.max(rect.p2.y) };
+    }
+
+    let align = |v: i32| v - v.rem_euclid(2);
+
+    (
+        Vector { x: align(min.x - MARGIN), y: align(min.y - MARGIN) },
+        Vector { x: align(max.x + MARGIN), y: align(max.y + MARGIN) },
+    )
+}
+
+/// List the cells of the path, from the start to `end`
+fn backtrack(parents: &HashMap<Node, Node>, end: Node) -> Vec<Vector<i32>> {
+    let mut nodes = vec![end.0.clone()];
+    let mut node = end;
+
+    while let Some(parent) = parents.get(&node) {
+        nodes.push(parent.0.clone());
+        node = parent.clone();
+    }
+
+    nodes.reverse();
+    nodes
+}
+
+/// Keep the cells where the path turns, the ends of the path are excluded
+fn corners(nodes: &[Vector<i32>]) -> Vec<Vector<i32>> {
+    nodes.windows(3)
+        .filter(|w| (w[1].x - w[0].x, w[1].y - w[0].y) != (w[2].x - w[1].x, w[2].y - w[1].y))
+        .map(|w| w[1].clone())
+        .collect()
+}
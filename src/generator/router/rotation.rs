@@ -0,0 +1,130 @@
+extern crate nalgebra as na;
+extern crate approx;
+
+use crate::generator::math::{Direction, Rectangle, Vector, intersects};
+use crate::generator::random;
+use super::{CorridorRouter, Route};
+use std::cmp;
+use rand_chacha::ChaCha8Rng;
+use rand::Rng;
+use na::{Point2, Rotation2};
+use approx::relative_eq;
+
+/// Original solver of the generator: the corridor is extended from the exit of
+/// the first room, turning toward the second room until reaching its exit.
+/// Corridors may go through rooms other than the two connected ones.
+pub struct RotationRouter {
+    pub path_extension: (u32, u32),
+}
+
+impl CorridorRouter for RotationRouter {
+    fn route(&mut self, route: &Route, rng: &mut ChaCha8Rng) -> Option<Vec<Vector<i32>>> {
+        let mut path = find_path(
+            route.from.clone(),
+            route.from_room,
+            route.from_direction,
+            route.to.clone(),
+            route.to_room,
+            self.path_extension,
+            rng,
+        )?;
+
+        // waypoints are found backward, from the destination
+        path.reverse();
+
+        Some(path)
+    }
+}
+
+/// Find a path going from one point to another, avoiding penetration into the destination room
+fn find_path(from_pos: Vector<i32>, from_rect: &Rectangle, from_dir: Direction, to_pos: Vector<i32>, to_rect: &Rectangle, path_extension: (u32, u32), rng: &mut ChaCha8Rng) -> Option<Vec<Vector<i32>>> {
+    let rot = match from_dir {
+        Direction::North => Rotation2::identity(),
+        Direction::South => Rotation2::new(std::f32::consts::FRAC_PI_2 * 2.0),
+        Direction::East => Rotation2::new(-std::f32::consts::FRAC_PI_2),
+        Direction::West => Rotation2::new(std::f32::consts::FRAC_PI_2),
+    };
+    let pos_from = rot * Point2::new(from_pos.x as f32, from_pos.y as f32);
+    let pos_to = rot * Point2::new(to_pos.x as f32, to_pos.y as f32);
+    let mut path = Vec::new();
+
+    find_next_waypoint(&mut path, pos_from, pos_to, from_rect, to_rect, rot.inverse(), 0, path_extension, rng)
+        .map(|_| path)
+}
+
+/// Find the next waypoint of the path.
+/// This method assumes points are rotated toward North (Y-)
+#[allow(clippy::too_many_arguments)]
+fn find_next_waypoint(path: &mut Vec<Vector<i32>>, pos_from: Point2<f32>, pos_to: Point2<f32>, rect_from: &Rectangle, rect_to: &Rectangle, inv: Rotation2<f32>, iteration: i8, path_extension: (u32, u32), rng: &mut ChaCha8Rng) -> Option<bool> {
+    if iteration > 10 {
+        return None;
+    }
+    let delta = pos_from.y - pos_to.y;
+    let mut pos_next = Point2::new(pos_from.x, pos_from.y);
+
+    if delta <= 0f32 {
+        pos_next.y -= random::gen_range(rng, path_extension.0..path_extension.1) as f32;
+        pos_next.y = if pos_next.y.round() as i32 % 2 == 0 { pos_next.y } else { pos_next.y + 1f32 };
+    }
+    else if iteration > 0
+    {
+        pos_next.y -= delta;
+        pos_next.y = if pos_next.y.round() as i32 % 2 == 0 { pos_next.y } else { pos_next.y + 1f32 };
+
+        // offset values to avoid collision with a room when the from or
+        // to positions are within the room (entrance & exit)
+        let from = cmp::min(pos_from.y.round() as i32, pos_to.y.round() as i32) + 1;
+        let to = cmp::max(pos_from.y.round() as i32, pos_to.y.round() as i32) - 1;
+        let p1 = inv * Point2::new(pos_from.x, from as f32);
+        let p2 = inv * Point2::new(pos_from.x, to as f32);
+
+        if intersects(p1, p2, rect_to, rect_from) {
+            return Some(false);
+        } else if relative_eq!(Point2::new(pos_next.x.round(), pos_next.y.round()), Point2::new(pos_to.x.round(), pos_to.y.round())) {
+            return Some(true);
+        }
+    }
+
+    let rot_delta = (pos_from.x - pos_to.x).round();
+    let next_rot = if relative_eq!(rot_delta.round(), 0f32) {
+        if rng.gen_range(0..1) == 0 {
+            Rotation2::new(-std::f32::consts::FRAC_PI_2)
+        }
+        else {
+            Rotation2::new(std::f32::consts::FRAC_PI_2)
+        }
+    }
+    else if rot_delta < 0f32 {
+        Rotation2::new(-std::f32::consts::FRAC_PI_2)
+    } else {
+        Rotation2::new(std::f32::consts::FRAC_PI_2)
+    };
+
+    let mut retry = 0;
+
+    loop {
+        match find_next_waypoint(path, next_rot * pos_next, next_rot * pos_to, rect_from, rect_to, inv * next_rot.inverse(), iteration + 1, path_extension, rng) {
+            Some(true) => {
+                let world_point = inv * pos_next;
+                let pos = Vector { x: world_point.x.round() as i32, y: world_point.y.round() as i32 };
+                path.push(pos);
+                break;
+            },
+            Some(false) => {
+                pos_next.y -= random::gen_range(rng, path_extension.0..path_extension.1) as f32;
+                pos_next.y = if pos_next.y.round() as i32 % 2 == 0 { pos_next.y } else { pos_next.y + 1f32 };
+            }
+            _ => {
+                return None;
+            },
+        }
+
+        retry += 1;
+
+        if retry > 5 {
+            return None;
+        }
+    }
+
+    Some(true)
+}
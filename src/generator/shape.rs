@@ -0,0 +1,436 @@
+use super::math::{Direction, Rectangle, Vector};
+use super::prefab::Prefab;
+use super::random;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Cells kept free in front of the anchors of a prefab, out of its bounds
+pub const APPROACH: i32 = 2;
+
+/// Cells kept between the floors of two rooms sharing part of their bounds,
+/// for a corridor and a wall on each side of it
+pub const GAP: i32 = 3;
+
+/// Kind of shape of a room, within its bounding rectangle
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoomShape {
+    Rectangle,
+    Ellipse,
+    /// Two arms crossing in the middle of the bounds
+    Cross,
+    /// L-shaped, a rectangle with one corner cut off
+    L,
+    /// Rectangle with four pillars, two cells away from the corners
+    Pillars,
+    /// Two or three overlapping rectangles
+    Composite,
+    /// Hand-authored template
+    Prefab,
+}
+
+/// Relative weights of the room shapes, a shape with a weight of 0 is never
+/// picked
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShapeWeights {
+    pub rectangle: u32,
+    pub ellipse: u32,
+    pub cross: u32,
+    pub l_shape: u32,
+    pub pillars: u32,
+    pub composite: u32,
+}
+
+impl Default for ShapeWeights {
+    fn default() -> Self {
+        ShapeWeights {
+            rectangle: 1,
+            ellipse: 0,
+            cross: 0,
+            l_shape: 0,
+            pillars: 0,
+            composite: 0,
+        }
+    }
+}
+
+impl ShapeWeights {
+    fn weights(&self) -> [(RoomShape, u32); 6] {
+        [
+            (RoomShape::Rectangle, self.rectangle),
+            (RoomShape::Ellipse, self.ellipse),
+            (RoomShape::Cross, self.cross),
+            (RoomShape::L, self.l_shape),
+            (RoomShape::Pillars, self.pillars),
+            (RoomShape::Composite, self.composite),
+        ]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.weights().iter().map(|(_, w)| *w as u64).sum()
+    }
+
+    /// Pick a random shape, the random generator is left untouched when a
+    /// single shape can be picked
+    pub fn pick(&self, rng: &mut ChaCha8Rng) -> RoomShape {
+        let weights = self.weights();
+        let mut candidates = weights.iter().filter(|(_, w)| *w > 0);
+
+        if let (Some((shape, _)), None) = (candidates.next(), candidates.next()) {
+            return *shape;
+        }
+
+        let mut value = random::gen_range(rng, 0..self.total() as i64) as u64;
+
+        for (shape, weight) in weights {
+            if value < weight as u64 {
+                return shape;
+            }
+
+            value -= weight as u64;
+        }
+
+        RoomShape::Rectangle
+    }
+}
+
+/// Cells of a room within its bounds. The parts are relative to the top left
+/// corner of the bounds.
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Rectangle,
+    Ellipse,
+    Cross([Rectangle; 2]),
+    L([Rectangle; 2]),
+    Pillars,
+    Composite(Vec<Rectangle>),
+    Prefab(Arc<Prefab>),
+}
+
+impl Shape {
+    /// Build a random shape of the given kind, fitting bounds of `size`
+    pub fn build(kind: RoomShape, size: &Vector<i32>, rng: &mut ChaCha8Rng) -> Shape {
+        let (w, h) = (size.x, size.y);
+        let part = |x1: i32, y1: i32, x2: i32, y2: i32| Rectangle {
+            p1: Vector { x: x1, y: y1 },
+            p2: Vector { x: x2, y: y2 },
+        };
+
+        match kind {
+            RoomShape::Rectangle => Shape::Rectangle,
+            RoomShape::Ellipse => Shape::Ellipse,
+            RoomShape::Pillars => Shape::Pillars,
+            RoomShape::Cross => {
+                // arms are at least 3 cells wide, to hold a door on an even cell
+                let aw = random::gen_range_inclusive(rng, (w / 3).max(3)..=(w / 2).max(3));
+                let ah = random::gen_range_inclusive(rng, (h / 3).max(3)..=(h / 2).max(3));
+                let (x, y) = ((w - aw) / 2, (h - ah) / 2);
+
+                Shape::Cross([part(x, 0, x + aw, h), part(0, y, w, y + ah)])
+            }
+            RoomShape::L => {
+                let nw = random::gen_range_inclusive(rng, (w / 3).max(1)..=(w / 2).max(1));
+                let nh = random::gen_range_inclusive(rng, (h / 3).max(1)..=(h / 2).max(1));
+                let parts = match random::gen_range(rng, 0..4) {
+                    0 => [part(nw, 0, w, h), part(0, nh, w, h)],
+                    1 => [part(0, 0, w - nw, h), part(0, nh, w, h)],
+                    2 => [part(nw, 0, w, h), part(0, 0, w, h - nh)],
+                    _ => [part(0, 0, w - nw, h), part(0, 0, w, h - nh)],
+                };
+
+                Shape::L(parts)
+            }
+            RoomShape::Composite => {
+                // parts wider & higher than half of the bounds always overlap
+                let random_size = |rng: &mut ChaCha8Rng| Vector {
+                    x: random::gen_range_inclusive(rng, (w / 2 + 1).min(w)..=w),
+                    y: random::gen_range_inclusive(rng, (h / 2 + 1).min(h)..=h),
+                };
+                let first = random_size(rng);
+                let second = random_size(rng);
+                let mut parts = vec![
+                    part(0, 0, first.x, first.y),
+                    part(w - second.x, h - second.y, w, h),
+                ];
+
+                if random::gen_range(rng, 0..2) == 0 {
+                    let third = random_size(rng);
+                    let x = random::gen_range_inclusive(rng, 0..=w - third.x);
+                    let y = random::gen_range_inclusive(rng, 0..=h - third.y);
+
+                    parts.push(part(x, y, x + third.x, y + third.y));
+                }
+
+                Shape::Composite(parts)
+            }
+            RoomShape::Prefab => unreachable!("prefabs are placed from their own weights"),
+        }
+    }
+
+    pub fn kind(&self) -> RoomShape {
+        match self {
+            Shape::Rectangle => RoomShape::Rectangle,
+            Shape::Ellipse => RoomShape::Ellipse,
+            Shape::Cross(_) => RoomShape::Cross,
+            Shape::L(_) => RoomShape::L,
+            Shape::Pillars => RoomShape::Pillars,
+            Shape::Composite(_) => RoomShape::Composite,
+            Shape::Prefab(_) => RoomShape::Prefab,
+        }
+    }
+
+    /// Test if a cell belongs to the room of the given bounds
+    pub fn contains(&self, bounds: &Rectangle, p: &Vector<i32>) -> bool {
+        if !bounds.is_inside(p.clone()) {
+            return false;
+        }
+
+        let local = Vector { x: p.x - bounds.p1.x, y: p.y - bounds.p1.y };
+        let (w, h) = (bounds.p2.x - bounds.p1.x, bounds.p2.y - bounds.p1.y);
+        let in_parts = |parts: &[Rectangle]| parts.iter().any(|r| r.is_inside(local.clone()));
+
+        match self {
+            Shape::Rectangle => true,
+            Shape::Ellipse => {
+                // distance of the center of the cell to the center of the bounds
+                let dx = (2 * local.x + 1 - w) as i64;
+                let dy = (2 * local.y + 1 - h) as i64;
+                let (w, h) = (w as i64, h as i64);
+
+                dx * dx * h * h + dy * dy * w * w <= w * w * h * h
+            }
+            Shape::Cross(parts) | Shape::L(parts) => in_parts(parts),
+            Shape::Composite(parts) => in_parts(parts),
+            Shape::Pillars => {
+                let pillar = |v: i32, size: i32| v == 2 || v == size - 3;
+
+                !(pillar(local.x, w) && pillar(local.y, h))
+            }
+            Shape::Prefab(prefab) => prefab.glyph(&local).is_some_and(|c| c != '.'),
+        }
+    }
+
+    /// Test if a cell of the room can be walked on, the walls of the prefabs
+    /// belong to the room but are not floor
+    pub fn is_floor(&self, bounds: &Rectangle, p: &Vector<i32>) -> bool {
+        match self {
+            Shape::Prefab(prefab) => {
+                let local = Vector { x: p.x - bounds.p1.x, y: p.y - bounds.p1.y };
+
+                prefab.glyph(&local).is_some_and(|c| matches!(c, 'x' | '#' | 'o'))
+            }
+            _ => self.contains(bounds, p),
+        }
+    }
+
+    /// List the cells of the room of the given bounds
+    pub fn cells<'a>(&'a self, bounds: &'a Rectangle) -> impl Iterator<Item = Vector<i32>> + 'a {
+        (bounds.p1.y..bounds.p2.y)
+            .flat_map(move |y| (bounds.p1.x..bounds.p2.x).map(move |x| Vector { x, y }))
+            .filter(move |p| self.contains(bounds, p))
+    }
+
+    /// List the tiles of the room which must stay walls, even when a corridor
+    /// runs through them: pillars and inner walls of the prefabs. Corridors
+    /// can cross the outline of a prefab, like the sides of the other rooms.
+    pub fn walls<'a>(&'a self, bounds: &'a Rectangle) -> impl Iterator<Item = Vector<i32>> + 'a {
+        Shape::Rectangle.cells(bounds).filter(move |p| match self {
+            Shape::Pillars => !self.contains(bounds, p),
+            Shape::Prefab(prefab) => {
+                let local = Vector { x: p.x - bounds.p1.x, y: p.y - bounds.p1.y };
+                let outline = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .any(|(dx, dy)| prefab.glyph(&Vector { x: local.x + dx, y: local.y + dy }).is_none_or(|c| c == '.'));
+
+                prefab.glyph(&local) == Some('w') && !outline
+            }
+            _ => false,
+        })
+    }
+
+    /// Test if two rooms share at least one cell, if the cells of two rooms
+    /// sharing part of their bounds are less than `GAP` cells apart, or if a
+    /// room stands in front of the anchors of a prefab
+    pub fn overlap(a: (&Rectangle, &Shape), b: (&Rectangle, &Shape)) -> bool {
+        // routers go round the bounds of the rooms, not their shapes
+        let blocks = |(rect, shape): (&Rectangle, &Shape), other: &Rectangle| {
+            shape.approaches(rect).into_iter().any(|p| other.is_inside(p))
+        };
+
+        if blocks(a, b.0) || blocks(b, a.0) {
+            return true;
+        }
+
+        if !a.0.overlap(b.0) {
+            return false;
+        }
+
+        if let (Shape::Rectangle, Shape::Rectangle) = (a.1, b.1) {
+            return true;
+        }
+
+        // the routers go round the bounds of the rooms, not their shapes, so
+        // nothing else keeps the corridors running between them off their
+        // floors
+        let near = Rectangle {
+            p1: Vector { x: a.0.p1.x.max(b.0.p1.x - GAP), y: a.0.p1.y.max(b.0.p1.y - GAP) },
+            p2: Vector { x: a.0.p2.x.min(b.0.p2.x + GAP), y: a.0.p2.y.min(b.0.p2.y + GAP) },
+        };
+        let around = |p: Vector<i32>| (p.y - GAP..=p.y + GAP)
+            .flat_map(move |y| (p.x - GAP..=p.x + GAP).map(move |x| Vector { x, y }));
+
+        (near.p1.y..near.p2.y)
+            .flat_map(|y| (near.p1.x..near.p2.x).map(move |x| Vector { x, y }))
+            .filter(|p| a.1.contains(a.0, p))
+            .any(|p| around(p).any(|q| b.1.contains(b.0, &q)))
+    }
+
+    /// Cells in front of the anchors of a prefab, which must stay free for
+    /// the corridors to reach the next even cell
+    fn approaches(&self, bounds: &Rectangle) -> Vec<Vector<i32>> {
+        let prefab = match self {
+            Shape::Prefab(prefab) => prefab,
+            _ => return Vec::new(),
+        };
+
+        prefab.anchors().iter()
+            .flat_map(|(anchor, direction)| {
+                let step = direction.step();
+
+                (1..=APPROACH).map(move |n| Vector {
+                    x: bounds.p1.x + anchor.x + step.x * n,
+                    y: bounds.p1.y + anchor.y + step.y * n,
+                })
+            })
+            .collect()
+    }
+
+    /// A door can be put on a cell of the side of the bounds facing
+    /// `direction` if the cell and the one behind it belong to the room, or
+    /// on the anchors of the prefabs
+    pub fn is_exit(&self, bounds: &Rectangle, position: &Vector<i32>, direction: Direction) -> bool {
+        let step = direction.step();
+        let inward = Vector { x: position.x - step.x, y: position.y - step.y };
+
+        if let Shape::Prefab(prefab) = self {
+            let local = Vector { x: position.x - bounds.p1.x, y: position.y - bounds.p1.y };
+
+            return prefab.anchors().iter().any(|(anchor, side)| *anchor == local && *side == direction);
+        }
+
+        self.contains(bounds, position) && self.contains(bounds, &inward)
+    }
+
+    /// Closest even cell from `position` where a door can be put, on the side
+    /// of the bounds facing `direction`
+    pub fn nearest_exit(&self, bounds: &Rectangle, position: &Vector<i32>, direction: Direction) -> Option<Vector<i32>> {
+        let side: Vec<Vector<i32>> = match direction {
+            Direction::North | Direction::South => (bounds.p1.x..bounds.p2.x).map(|x| Vector { x, y: position.y }).collect(),
+            Direction::East | Direction::West => (bounds.p1.y..bounds.p2.y).map(|y| Vector { x: position.x, y }).collect(),
+        };
+
+        side.into_iter()
+            .filter(|p| p.x % 2 == 0 && p.y % 2 == 0 && self.is_exit(bounds, p, direction))
+            .min_by_key(|p| (p.x - position.x).abs() + (p.y - position.y).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    fn rect(x1: i32, y1: i32, x2: i32, y2: i32) -> Rectangle {
+        Rectangle { p1: Vector { x: x1, y: y1 }, p2: Vector { x: x2, y: y2 } }
+    }
+
+    #[test]
+    fn cells_of_the_built_shapes_are_connected_and_in_bounds() {
+        let kinds = [RoomShape::Rectangle, RoomShape::Ellipse, RoomShape::Cross, RoomShape::L, RoomShape::Pillars, RoomShape::Composite];
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for kind in kinds {
+            for (w, h) in [(5, 5), (7, 5), (9, 13)] {
+                let bounds = rect(2, 4, 2 + w, 4 + h);
+                let shape = Shape::build(kind, &Vector { x: w, y: h }, &mut rng);
+                let cells: HashSet<Vector<i32>> = shape.cells(&bounds).collect();
+
+                assert!(cells.iter().all(|p| bounds.is_inside(p.clone())), "{:?} {}x{}", kind, w, h);
+
+                // flood the cells from the first one
+                let mut stack = vec![cells.iter().min().unwrap().clone()];
+                let mut reached = HashSet::new();
+
+                while let Some(p) = stack.pop() {
+                    if cells.contains(&p) && reached.insert(p.clone()) {
+                        stack.extend([Direction::North, Direction::South, Direction::East, Direction::West].map(|d| p.clone() + d.step()));
+                    }
+                }
+
+                assert_eq!(reached.len(), cells.len(), "{:?} {}x{}", kind, w, h);
+            }
+        }
+    }
+
+    #[test]
+    fn pillars_are_the_only_walls_of_the_procedural_rooms() {
+        let bounds = rect(0, 0, 7, 9);
+
+        assert_eq!(Shape::Rectangle.cells(&bounds).count(), 63);
+        assert_eq!(Shape::Rectangle.walls(&bounds).count(), 0);
+        assert_eq!(Shape::Pillars.cells(&bounds).count(), 59);
+        assert_eq!(Shape::Pillars.walls(&bounds).collect::<Vec<_>>(), vec![
+            Vector { x: 2, y: 2 }, Vector { x: 4, y: 2 }, Vector { x: 2, y: 6 }, Vector { x: 4, y: 6 },
+        ]);
+    }
+
+    #[test]
+    fn walls_of_a_prefab_leave_out_its_outline() {
+        let prefab = Prefab::parse("wwwwwww\nwxxxxxw\nwxwwwxw\nwxxxxxw\nwwDwwww").unwrap();
+        let shape = Shape::Prefab(Arc::new(prefab));
+        let bounds = rect(10, 10, 17, 15);
+
+        assert_eq!(shape.cells(&bounds).count(), 35);
+        assert_eq!(shape.walls(&bounds).collect::<Vec<_>>(), vec![
+            Vector { x: 12, y: 12 }, Vector { x: 13, y: 12 }, Vector { x: 14, y: 12 },
+        ]);
+    }
+
+    #[test]
+    fn shapes_overlap_when_they_share_a_cell() {
+        let a = rect(0, 0, 9, 9);
+
+        assert!(Shape::overlap((&a, &Shape::Rectangle), (&rect(8, 8, 13, 13), &Shape::Rectangle)));
+        assert!(!Shape::overlap((&a, &Shape::Rectangle), (&rect(9, 0, 13, 5), &Shape::Rectangle)));
+        assert!(Shape::overlap((&a, &Shape::Ellipse), (&rect(4, 4, 13, 13), &Shape::Ellipse)));
+    }
+
+    #[test]
+    fn shapes_sharing_their_bounds_keep_a_gap_between_their_floors() {
+        let a = rect(0, 0, 9, 9);
+        let cross = Shape::Cross([rect(5, 0, 8, 13), rect(0, 5, 13, 8)]);
+
+        // the corners of the bounds of two ellipses are empty
+        assert!(Shape::overlap((&a, &Shape::Ellipse), (&rect(8, 8, 17, 17), &Shape::Ellipse)));
+        assert!(!Shape::overlap((&a, &Shape::Ellipse), (&rect(10, 10, 19, 19), &Shape::Ellipse)));
+
+        // rooms in the empty corner of a cross
+        assert!(!Shape::overlap((&rect(0, 0, 13, 13), &cross), (&rect(-4, -4, 5 - GAP, 5 - GAP), &Shape::Rectangle)));
+        assert!(Shape::overlap((&rect(0, 0, 13, 13), &cross), (&rect(-4, -4, 5 - GAP + 1, 5 - GAP + 1), &Shape::Rectangle)));
+    }
+
+    #[test]
+    fn rooms_in_front_of_an_anchor_overlap_the_prefab() {
+        let prefab = Prefab::parse("wwwww\nwxxxw\nwxxxw\nwxxxw\nwwDww").unwrap();
+        let shape = Shape::Prefab(Arc::new(prefab));
+        let bounds = rect(0, 0, 5, 5);
+
+        // right below the anchor, without sharing a cell
+        assert!(Shape::overlap((&bounds, &shape), (&rect(0, 5 + APPROACH - 1, 5, 12), &Shape::Rectangle)));
+        assert!(!Shape::overlap((&bounds, &shape), (&rect(0, 5 + APPROACH, 5, 12), &Shape::Rectangle)));
+    }
+}
@@ -0,0 +1,104 @@
+use super::math::{Rectangle, Vector};
+use std::collections::HashMap;
+
+/// Side of the cells of the grid, about twice the size of the default rooms
+const CELL_SIZE: u32 = 16;
+
+/// Uniform grid over the bounds of the rooms, to find the rooms near a point
+/// or an area without testing every one of them. Rooms are indexed in the
+/// order they are added, like `Dungeon::rooms`.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex {
+    cell: i32,
+    rects: Vec<Rectangle>,
+    /// Rooms whose bounds cover each cell of the grid
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    bounds: Option<Rectangle>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        SpatialIndex::new(CELL_SIZE)
+    }
+}
+
+impl SpatialIndex {
+    pub fn new(cell: u32) -> SpatialIndex {
+        SpatialIndex {
+            cell: cell.clamp(1, i32::MAX as u32) as i32,
+            rects: Vec::new(),
+            cells: HashMap::new(),
+            bounds: None,
+        }
+    }
+
+    /// Add the bounds of a room, returns its index
+    pub fn insert(&mut self, rect: Rectangle) -> usize {
+        let index = self.rects.len();
+
+        for cell in self.cells_of(&rect) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+
+        self.bounds = Some(match self.bounds.take() {
+            Some(b) => Rectangle {
+                p1: Vector { x: b.p1.x.min(rect.p1.x), y: b.p1.y.min(rect.p1.y) },
+                p2: Vector { x: b.p2.x.max(rect.p2.x), y: b.p2.y.max(rect.p2.y) },
+            },
+            None => rect.clone(),
+        });
+        self.rects.push(rect);
+
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Bounds of every room, by index
+    pub fn rects(&self) -> &[Rectangle] {
+        &self.rects
+    }
+
+    /// Smallest rectangle holding every room, `None` without rooms
+    pub fn bounds(&self) -> Option<&Rectangle> {
+        self.bounds.as_ref()
+    }
+
+    /// Test if a point is inside the bounds of any room
+    pub fn contains(&self, p: &Vector<i32>) -> bool {
+        self.cells.get(&self.cell_of(p))
+            .is_some_and(|rooms| rooms.iter().any(|i| self.rects[*i].is_inside(p.clone())))
+    }
+
+    /// Indices of the rooms whose bounds overlap the area, in ascending order
+    pub fn query(&self, area: &Rectangle) -> Vec<usize> {
+        let mut rooms: Vec<usize> = self.cells_of(area)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(|i| self.rects[*i].overlap(area))
+            .collect();
+
+        rooms.sort_unstable();
+        rooms.dedup();
+        rooms
+    }
+
+    fn cell_of(&self, p: &Vector<i32>) -> (i32, i32) {
+        (p.x.div_euclid(self.cell), p.y.div_euclid(self.cell))
+    }
+
+    /// Cells covered by a rectangle, `p2` being exclusive
+    fn cells_of(&self, rect: &Rectangle) -> impl Iterator<Item = (i32, i32)> {
+        let (x1, y1) = self.cell_of(&rect.p1);
+        let (x2, y2) = self.cell_of(&Vector { x: rect.p2.x - 1, y: rect.p2.y - 1 });
+
+        (y1..=y2).flat_map(move |y| (x1..=x2).map(move |x| (x, y)))
+    }
+}
@@ -0,0 +1,53 @@
+mod bsp;
+pub(super) mod cave;
+mod scatter;
+
+use crate::Config;
+use super::dungeon::Dungeon;
+use super::errors::{ConfigError, GenerationError};
+use serde::{Deserialize, Serialize};
+
+pub use cave::{CaveRules, Pockets};
+
+/// Algorithm placing the rooms, selected in the `Config`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Attach each room next to a random existing room
+    Scatter,
+    /// Split the `bounds` of the config recursively, one room per leaf
+    Bsp,
+    /// Carve caverns in the `bounds` of the config with a cellular automaton,
+    /// following the `cave` rules of the config
+    Cave,
+}
+
+/// Code of the `strategy` of a `Config`
+impl TryFrom<u32> for Strategy {
+    type Error = ConfigError;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            0 => Ok(Strategy::Scatter),
+            1 => Ok(Strategy::Bsp),
+            2 => Ok(Strategy::Cave),
+            _ => Err(ConfigError::UnknownCode { field: "strategy", value: code }),
+        }
+    }
+}
+
+impl From<Strategy> for u32 {
+    fn from(strategy: Strategy) -> Self {
+        strategy as u32
+    }
+}
+
+/// Place the rooms of the dungeon and connect them
+pub(super) fn place_rooms(dungeon: &mut Dungeon, config: &Config) -> Result<(), GenerationError> {
+    match config.strategy()? {
+        Strategy::Scatter => scatter::place_rooms(dungeon, config),
+        Strategy::Bsp => bsp::place_rooms(dungeon, config),
+        Strategy::Cave => unreachable!("caves are generated without rooms"),
+    }
+}
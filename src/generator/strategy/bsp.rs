@@ -0,0 +1,274 @@
+use crate::Config;
+use crate::generator::dungeon::{Dungeon, RoomId};
+use crate::generator::errors::GenerationError;
+use crate::generator::math::{Rectangle, Vector};
+use crate::generator::prefab::{self, Prefab};
+use crate::generator::random;
+use crate::generator::shape::Shape;
+use rand_chacha::ChaCha8Rng;
+use std::sync::Arc;
+
+/// Node of the partition tree, the leaves hold the rooms
+struct Node {
+    area: Rectangle,
+    children: Option<(usize, usize)>,
+    room: Option<RoomId>,
+}
+
+/// Prefab put in a leaf of the tree
+struct Placement {
+    leaf: usize,
+    rect: Rectangle,
+    prefab: Arc<Prefab>,
+}
+
+/// Split the bounds of the config until there are as many leaves as rooms to
+/// place (or until the leaves get too small), put one room in each leaf and
+/// connect the closest rooms of every pair of siblings
+pub fn place_rooms(dungeon: &mut Dungeon, config: &Config) -> Result<(), GenerationError> {
+    let overflow = |_| GenerationError::CoordinateOverflow;
+    let bounds = Rectangle {
+        p1: Vector { x: 0, y: 0 },
+        p2: Vector { x: i32::try_from(config.bounds.x).map_err(overflow)?, y: i32::try_from(config.bounds.y).map_err(overflow)? },
+    };
+    let padding = (config.rooms_spacing.min / 2).max(1) as i32;
+    // smallest leaf able to hold a room of the minimum size, once aligned
+    let min_leaf = Vector {
+        x: dungeon.min_size.x as i32 + 2 * padding + 2,
+        y: dungeon.min_size.y as i32 + 2 * padding + 2,
+    };
+    let mut nodes = vec![Node { area: bounds, children: None, room: None }];
+    let mut leaves = 1;
+
+    while leaves < config.rooms_count {
+        // split the biggest leaf first, to keep the partition balanced
+        let candidate = nodes.iter().enumerate()
+            .filter(|(_, n)| n.children.is_none() && can_split(&n.area, &min_leaf))
+            .max_by_key(|(i, n)| (area(&n.area), usize::MAX - i))
+            .map(|(i, _)| i);
+
+        let index = match candidate {
+            Some(index) => index,
+            None => break,
+        };
+
+        let (a, b) = split(&nodes[index].area, &min_leaf, &mut dungeon.rng);
+
+        nodes.push(Node { area: a, children: None, room: None });
+        nodes.push(Node { area: b, children: None, room: None });
+        nodes[index].children = Some((nodes.len() - 2, nodes.len() - 1));
+        leaves += 1;
+    }
+
+    let prefabs = assign_prefabs(dungeon, &mut nodes, padding)?;
+
+    for index in depth_first(&nodes, 0).collect::<Vec<_>>() {
+        if nodes[index].children.is_some() {
+            continue;
+        }
+
+        if let Some(placement) = prefabs.iter().find(|p| p.leaf == index) {
+            let id = dungeon.add_room(placement.rect.clone(), Shape::Prefab(placement.prefab.clone()));
+
+            nodes[index].room = Some(id);
+        } else if let Some(rect) = room_in(&nodes[index].area, padding, dungeon) {
+            let kind = dungeon.shapes.pick(&mut dungeon.rng);
+            let shape = Shape::build(kind, &Vector { x: rect.p2.x - rect.p1.x, y: rect.p2.y - rect.p1.y }, &mut dungeon.rng);
+
+            let id = dungeon.add_room(rect, shape);
+
+            nodes[index].room = Some(id);
+        }
+    }
+
+    if dungeon.rooms.is_empty() {
+        return Err(GenerationError::PlacementExhausted { room: 0 });
+    }
+
+    // connect the siblings, from the deepest nodes to the root
+    for index in (0..nodes.len()).rev() {
+        if let Some((left, right)) = nodes[index].children {
+            let pair = nearest_rooms(dungeon, &rooms_under(&nodes, left), &rooms_under(&nodes, right));
+
+            if let Some((a, b)) = pair {
+                dungeon.connect_rooms(a, b);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Put the prefabs chosen for the dungeon in the biggest leaves they fit in,
+/// returns the index of the leaf and the position of each prefab. The
+/// prefabs which fit in no leaf are left out, unless they are under their
+/// min count: the leaves of the smallest branch able to hold them are then
+/// merged into one.
+fn assign_prefabs(dungeon: &mut Dungeon, nodes: &mut [Node], padding: i32) -> Result<Vec<Placement>, GenerationError> {
+    let mut leaves: Vec<usize> = (0..nodes.len()).filter(|i| nodes[*i].children.is_none()).collect();
+    let plan = prefab::plan(&dungeon.prefabs, leaves.len(), &mut dungeon.rng)?;
+    let mut instances: Vec<Arc<Prefab>> = plan.into_iter().flatten().map(|i| dungeon.prefabs[i].clone()).collect();
+    let mut assigned: Vec<Placement> = Vec::new();
+
+    leaves.sort_by_key(|i| -area(&nodes[*i].area));
+    instances.sort_by_key(|p| -(p.size().x as i64 * p.size().y as i64));
+
+    for prefab in instances {
+        let size = prefab.size();
+        let mut position = leaves.iter().enumerate()
+            .find_map(|(n, leaf)| Some((n, place_prefab(&nodes[*leaf].area, &size, padding, &mut dungeon.rng)?)));
+        let required = assigned.iter().filter(|p| Arc::ptr_eq(&p.prefab, &prefab)).count() < prefab.min as usize;
+
+        if position.is_none() && required {
+            // whatever the partition, the bounds are too small
+            if place_prefab(&nodes[0].area, &size, padding, &mut dungeon.rng.clone()).is_none() {
+                return Err(GenerationError::InvalidConfig(format!("Prefab {} does not fit in the bounds", prefab.name)));
+            }
+
+            let taken = |index: usize| depth_first(nodes, index).any(|i| assigned.iter().any(|p| p.leaf == i));
+            let branch = depth_first(nodes, 0)
+                .filter(|i| nodes[*i].children.is_some() && !taken(*i))
+                .filter(|i| place_prefab(&nodes[*i].area, &size, padding, &mut dungeon.rng.clone()).is_some())
+                .min_by_key(|i| (area(&nodes[*i].area), *i))
+                .ok_or(GenerationError::PlacementExhausted { room: dungeon.rooms.len() })?;
+            let merged: Vec<usize> = depth_first(nodes, branch).collect();
+
+            leaves.retain(|leaf| !merged.contains(leaf));
+            leaves.push(branch);
+            nodes[branch].children = None;
+            position = place_prefab(&nodes[branch].area, &size, padding, &mut dungeon.rng).map(|p| (leaves.len() - 1, p));
+        }
+
+        if let Some((n, p1)) = position {
+            let leaf = leaves.remove(n);
+            let p2 = Vector { x: p1.x + size.x, y: p1.y + size.y };
+
+            assigned.push(Placement { leaf, rect: Rectangle { p1, p2 }, prefab });
+        }
+    }
+
+    Ok(assigned)
+}
+
+/// Position of a prefab of `size` in the area, keeping `padding` cells free
+/// on each side. Returns `None` if the area is too small.
+fn place_prefab(area: &Rectangle, size: &Vector<i32>, padding: i32, rng: &mut ChaCha8Rng) -> Option<Vector<i32>> {
+    let x = fit(area.p1.x + padding, area.p2.x - padding, size.x as u32, size.x as u32, rng)?;
+    let y = fit(area.p1.y + padding, area.p2.y - padding, size.y as u32, size.y as u32, rng)?;
+
+    Some(Vector { x: x.0, y: y.0 })
+}
+
+fn area(rect: &Rectangle) -> i64 {
+    (rect.p2.x - rect.p1.x) as i64 * (rect.p2.y - rect.p1.y) as i64
+}
+
+fn can_split(rect: &Rectangle, min_leaf: &Vector<i32>) -> bool {
+    rect.p2.x - rect.p1.x >= 2 * min_leaf.x || rect.p2.y - rect.p1.y >= 2 * min_leaf.y
+}
+
+/// Cut the rectangle across its longest side, on an even cell
+fn split(rect: &Rectangle, min_leaf: &Vector<i32>, rng: &mut ChaCha8Rng) -> (Rectangle, Rectangle) {
+    let width = rect.p2.x - rect.p1.x;
+    let height = rect.p2.y - rect.p1.y;
+    let vertical = if width < 2 * min_leaf.x {
+        false
+    } else if height < 2 * min_leaf.y || width * 4 > height * 5 {
+        true
+    } else if height * 4 > width * 5 {
+        false
+    } else {
+        random::gen_range(rng, 0..2) == 0
+    };
+
+    let cut = |lo: i32, hi: i32, rng: &mut ChaCha8Rng| {
+        let position = random::gen_range_inclusive(rng, lo..=hi);
+
+        position - position.rem_euclid(2)
+    };
+
+    if vertical {
+        let x = cut(rect.p1.x + min_leaf.x, rect.p2.x - min_leaf.x, rng);
+
+        (
+            Rectangle { p1: rect.p1.clone(), p2: Vector { x, y: rect.p2.y } },
+            Rectangle { p1: Vector { x, y: rect.p1.y }, p2: rect.p2.clone() },
+        )
+    } else {
+        let y = cut(rect.p1.y + min_leaf.y, rect.p2.y - min_leaf.y, rng);
+
+        (
+            Rectangle { p1: rect.p1.clone(), p2: Vector { x: rect.p2.x, y } },
+            Rectangle { p1: Vector { x: rect.p1.x, y }, p2: rect.p2.clone() },
+        )
+    }
+}
+
+/// Place a room of random size in the leaf, keeping `padding` cells free on
+/// each side. Returns `None` if the leaf is too small.
+fn room_in(leaf: &Rectangle, padding: i32, dungeon: &mut Dungeon) -> Option<Rectangle> {
+    let (x1, x2) = fit(leaf.p1.x + padding, leaf.p2.x - padding, dungeon.min_size.x, dungeon.max_size.x, &mut dungeon.rng)?;
+    let (y1, y2) = fit(leaf.p1.y + padding, leaf.p2.y - padding, dungeon.min_size.y, dungeon.max_size.y, &mut dungeon.rng)?;
+
+    Some(Rectangle {
+        p1: Vector { x: x1, y: y1 },
+        p2: Vector { x: x2, y: y2 },
+    })
+}
+
+/// Find a span of random length within `[lo, hi]` starting on an even cell
+/// and ending on an odd cell (excluded), like the rooms of the other layouts
+fn fit(lo: i32, hi: i32, min: u32, max: u32, rng: &mut ChaCha8Rng) -> Option<(i32, i32)> {
+    let lo = lo + lo.rem_euclid(2);
+    let space = hi - lo;
+    let max = (max as i32).min(space);
+
+    if max < min as i32 {
+        return None;
+    }
+
+    let size = random::gen_range_inclusive(rng, min as i32..=max);
+    // align the end to odd cells on grid
+    let size = if size % 2 == 1 { size } else if size < space { size + 1 } else { size - 1 };
+
+    if size < min as i32 {
+        return None;
+    }
+
+    let offset = random::gen_range_inclusive(rng, 0..=(space - size) / 2) * 2;
+
+    Some((lo + offset, lo + offset + size))
+}
+
+fn rooms_under(nodes: &[Node], index: usize) -> Vec<RoomId> {
+    depth_first(nodes, index).filter_map(|i| nodes[i].room).collect()
+}
+
+/// Indices of the node and its descendants, left children first
+fn depth_first(nodes: &[Node], index: usize) -> impl Iterator<Item = usize> + '_ {
+    let mut stack = vec![index];
+
+    std::iter::from_fn(move || {
+        let index = stack.pop()?;
+
+        if let Some((left, right)) = nodes[index].children {
+            stack.push(right);
+            stack.push(left);
+        }
+
+        Some(index)
+    })
+}
+
+/// Closest pair of rooms, one from each list
+fn nearest_rooms(dungeon: &Dungeon, a: &[RoomId], b: &[RoomId]) -> Option<(RoomId, RoomId)> {
+    let center = |id: RoomId| dungeon.rooms[id.0].rect.center();
+
+    a.iter()
+        .flat_map(|i| b.iter().map(move |j| (*i, *j)))
+        .min_by_key(|(i, j)| {
+            let (p, q) = (center(*i), center(*j));
+
+            (p.x - q.x).pow(2) + (p.y - q.y).pow(2)
+        })
+}
@@ -0,0 +1,366 @@
+use crate::Config;
+use crate::generator::errors::{ConfigError, GenerationError};
+use crate::generator::layout::{ConnectionLayout, DungeonLayout, RoomLayout};
+use crate::generator::map::Map;
+use crate::generator::math::{Rectangle, Vector};
+use crate::generator::shape::RoomShape;
+use crate::generator::random;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (0, 1), (1, 0), (-1, 0)];
+
+/// What to do with the caverns left apart from the biggest one
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pockets {
+    /// Fill them back with rock
+    Cull,
+    /// Dig a corridor from each of them to the nearest connected cavern
+    Join,
+}
+
+/// Rules of the cellular automaton carving the caves
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaveRules {
+    /// Percentage of the cells filled with rock before the first iteration
+    pub fill: u32,
+    /// An open cell turns into rock when at least `birth` of its 8
+    /// neighbours are rock
+    pub birth: u32,
+    /// A rock cell stays rock when at least `survival` of its 8 neighbours
+    /// are rock
+    pub survival: u32,
+    pub iterations: u32,
+    /// Caverns with fewer cells are always filled back with rock
+    pub min_pocket: u32,
+    /// Code of a `Pockets`
+    #[serde(serialize_with = "crate::code::serialize::<Pockets, _>", deserialize_with = "crate::code::deserialize::<Pockets, _>")]
+    pub pockets: u32,
+}
+
+impl Default for CaveRules {
+    fn default() -> Self {
+        CaveRules {
+            fill: 45,
+            birth: 5,
+            survival: 4,
+            iterations: 4,
+            min_pocket: 8,
+            pockets: Pockets::Join.into(),
+        }
+    }
+}
+
+impl CaveRules {
+    pub fn pockets(&self) -> Result<Pockets, ConfigError> {
+        Pockets::try_from(self.pockets)
+    }
+}
+
+/// Code of the `cave.pockets` of a `Config`
+impl TryFrom<u32> for Pockets {
+    type Error = ConfigError;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            0 => Ok(Pockets::Cull),
+            1 => Ok(Pockets::Join),
+            _ => Err(ConfigError::UnknownCode { field: "cave.pockets", value: code }),
+        }
+    }
+}
+
+impl From<Pockets> for u32 {
+    fn from(pockets: Pockets) -> Self {
+        pockets as u32
+    }
+}
+
+/// Rock & open cells of the cave, the cells outside of the grid are rock
+struct Grid {
+    width: i32,
+    height: i32,
+    rock: Vec<bool>,
+}
+
+impl Grid {
+    fn is_rock(&self, x: i32, y: i32) -> bool {
+        x < 0 || y < 0 || x >= self.width || y >= self.height || self.rock[self.index(x, y)]
+    }
+
+    fn rock_neighbours(&self, x: i32, y: i32) -> u32 {
+        let mut count = 0;
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) && self.is_rock(x + dx, y + dy) {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    fn step(&self, rules: &CaveRules) -> Grid {
+        let rock = (0..self.rock.len())
+            .map(|n| {
+                let (x, y) = self.position(n);
+                let count = self.rock_neighbours(x, y);
+
+                if self.rock[n] { count >= rules.survival } else { count >= rules.birth }
+            })
+            .collect();
+
+        Grid { width: self.width, height: self.height, rock }
+    }
+
+    /// Group the open cells walkable from one another, biggest caverns first
+    fn pockets(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.rock.len()];
+        let mut pockets = Vec::new();
+
+        for start in 0..self.rock.len() {
+            if visited[start] || self.rock[start] {
+                continue;
+            }
+
+            let mut pocket = Vec::new();
+            let mut stack = vec![start];
+
+            visited[start] = true;
+
+            while let Some(n) = stack.pop() {
+                pocket.push(n);
+
+                for next in self.neighbours(n) {
+                    if !visited[next] && !self.rock[next] {
+                        visited[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+
+            pocket.sort_unstable();
+            pockets.push(pocket);
+        }
+
+        pockets.sort_by_key(|p| usize::MAX - p.len());
+        pockets
+    }
+
+    fn neighbours(&self, n: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = self.position(n);
+
+        NEIGHBOURS.iter()
+            .map(move |(dx, dy)| (x + dx, y + dy))
+            .filter(|(x, y)| *x >= 0 && *y >= 0 && *x < self.width && *y < self.height)
+            .map(|(x, y)| self.index(x, y))
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (x + y * self.width) as usize
+    }
+
+    fn position(&self, n: usize) -> (i32, i32) {
+        ((n % self.width as usize) as i32, (n / self.width as usize) as i32)
+    }
+}
+
+/// Corridor dug between two caverns
+struct Tunnel {
+    from: usize,
+    to: usize,
+    /// Cells of the corridor, from the `from` cavern to the `to` one
+    cells: Vec<usize>,
+}
+
+/// Carve caverns in the `bounds` of the config with a cellular automaton.
+/// Each cavern is reported as a room of the layout, with its bounding box,
+/// and each corridor joining two caverns as a connection.
+pub fn generate(config: &Config) -> Result<(Map, DungeonLayout), GenerationError> {
+    let rules = &config.cave;
+    let overflow = |_| GenerationError::CoordinateOverflow;
+    let width = i32::try_from(config.bounds.x).map_err(overflow)?;
+    let height = i32::try_from(config.bounds.y).map_err(overflow)?;
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let cells = (width as usize).checked_mul(height as usize).ok_or(GenerationError::CoordinateOverflow)?;
+    let mut grid = Grid {
+        width,
+        height,
+        rock: (0..cells).map(|_| random::gen_range(&mut rng, 0..100u32) < rules.fill).collect(),
+    };
+
+    for _ in 0..rules.iterations {
+        grid = grid.step(rules);
+    }
+
+    let mut pockets = grid.pockets();
+
+    if rules.pockets()? == Pockets::Cull {
+        pockets.truncate(1);
+    }
+
+    pockets.retain(|p| p.len() >= rules.min_pocket as usize);
+
+    if pockets.is_empty() {
+        return Err(GenerationError::PlacementExhausted { room: 0 });
+    }
+
+    let tunnels = join(&grid, &pockets);
+    let mut map = Map::build();
+
+    map.resize(&Vector { x: -1, y: -1 }, &Vector { x: width, y: height })?;
+
+    for n in pockets.iter().flatten() {
+        let (x, y) = grid.position(*n);
+
+        map.add_floor(&Vector { x, y });
+    }
+
+    for n in tunnels.iter().flat_map(|t| t.cells.iter()) {
+        let (x, y) = grid.position(*n);
+
+        map.add_corridor(&Vector { x, y }, &Vector { x, y });
+    }
+
+    map.add_walls(config.walls()?);
+
+    let layout = to_layout(&grid, &pockets, &tunnels, map.offset());
+
+    Ok((map, layout))
+}
+
+/// Dig a corridor from each cavern to the nearest cavern already connected
+/// to the biggest one, until every cavern is connected
+fn join(grid: &Grid, pockets: &[Vec<usize>]) -> Vec<Tunnel> {
+    let mut owner: Vec<Option<usize>> = vec![None; grid.rock.len()];
+    let mut connected = vec![false; pockets.len()];
+    let mut tunnels: Vec<Tunnel> = Vec::new();
+    // distance to the closest connected cell, updated as caverns get connected
+    let mut distances = vec![u32::MAX; grid.rock.len()];
+    let mut parents: Vec<Option<usize>> = vec![None; grid.rock.len()];
+    let mut open = BinaryHeap::new();
+
+    for (id, pocket) in pockets.iter().enumerate() {
+        for n in pocket {
+            owner[*n] = Some(id);
+        }
+    }
+
+    connected[0] = true;
+
+    for n in pockets[0].iter() {
+        distances[*n] = 0;
+        open.push(Reverse((0, *n)));
+    }
+
+    while let Some(Reverse((distance, n))) = open.pop() {
+        if distance > distances[n] {
+            continue;
+        }
+
+        let from = match owner[n] {
+            Some(id) if !connected[id] => id,
+            _ => {
+                for next in grid.neighbours(n) {
+                    if distance + 1 < distances[next] {
+                        distances[next] = distance + 1;
+                        parents[next] = Some(n);
+                        open.push(Reverse((distance + 1, next)));
+                    }
+                }
+
+                continue;
+            }
+        };
+
+        // first cell of an isolated cavern popped, it is the closest one
+        let mut cells = Vec::new();
+        let mut cell = n;
+
+        while let Some(parent) = parents[cell].filter(|_| distances[cell] > 0) {
+            cells.push(parent);
+            cell = parent;
+        }
+
+        // the last cell is inside a connected cavern or tunnel
+        cells.pop();
+
+        let to = owner[cell].unwrap_or(0);
+
+        connected[from] = true;
+
+        for n in pockets[from].iter().chain(cells.iter()) {
+            owner[*n] = Some(owner[*n].unwrap_or(to));
+            distances[*n] = 0;
+            open.push(Reverse((0, *n)));
+        }
+
+        // a previous tunnel may already run along the cavern
+        if !cells.is_empty() {
+            tunnels.push(Tunnel { from, to, cells });
+        }
+    }
+
+    tunnels
+}
+
+fn to_layout(grid: &Grid, pockets: &[Vec<usize>], tunnels: &[Tunnel], offset: &Vector<i32>) -> DungeonLayout {
+    let point = |n: usize| {
+        let (x, y) = grid.position(n);
+
+        Vector { x: x + offset.x, y: y + offset.y }
+    };
+
+    let rooms = pockets.iter().enumerate()
+        .map(|(id, pocket)| {
+            let points: Vec<Vector<i32>> = pocket.iter().map(|n| point(*n)).collect();
+            let min = |f: fn(&Vector<i32>) -> i32| points.iter().map(f).min().unwrap_or(0);
+            let max = |f: fn(&Vector<i32>) -> i32| points.iter().map(f).max().unwrap_or(0) + 1;
+
+            RoomLayout {
+                id,
+                bounds: Rectangle {
+                    p1: Vector { x: min(|p| p.x), y: min(|p| p.y) },
+                    p2: Vector { x: max(|p| p.x), y: max(|p| p.y) },
+                },
+                shape: RoomShape::Composite,
+            }
+        })
+        .collect();
+
+    let connections = tunnels.iter().enumerate()
+        .map(|(id, tunnel)| {
+            let points: Vec<Vector<i32>> = tunnel.cells.iter().map(|n| point(*n)).collect();
+            let mut waypoints: Vec<Vector<i32>> = points.windows(3)
+                .filter(|w| (w[1].x - w[0].x, w[1].y - w[0].y) != (w[2].x - w[1].x, w[2].y - w[1].y))
+                .map(|w| w[1].clone())
+                .collect();
+
+            waypoints.insert(0, points[0].clone());
+
+            if points.len() > 1 {
+                waypoints.push(points[points.len() - 1].clone());
+            }
+
+            ConnectionLayout {
+                id,
+                from: tunnel.from,
+                to: tunnel.to,
+                from_door: points[0].clone(),
+                to_door: points[points.len() - 1].clone(),
+                waypoints,
+            }
+        })
+        .collect();
+
+    DungeonLayout { rooms, connections, prefabs: Vec::new(), repairs: Vec::new() }
+}
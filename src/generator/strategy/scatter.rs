@@ -0,0 +1,71 @@
+use crate::Config;
+use crate::generator::dungeon::{Dungeon, RoomId};
+use crate::generator::errors::GenerationError;
+use crate::generator::math::{Rectangle, Vector};
+use crate::generator::planner;
+use crate::generator::prefab;
+use crate::generator::random;
+use crate::generator::shape::Shape;
+
+/// Put each room next to a random existing room, then connect the rooms
+/// along the spanning tree of their centres, plus a few loops
+pub fn place_rooms(dungeon: &mut Dungeon, config: &Config) -> Result<(), GenerationError> {
+    let plan = prefab::plan(&dungeon.prefabs, config.rooms_count, &mut dungeon.rng)?;
+    let mut rng = dungeon.rng.clone();
+
+    for (i, prefab) in plan.into_iter().enumerate() {
+        add_room(dungeon, i, prefab)?;
+    }
+
+    let centers: Vec<Vector<i32>> = dungeon.rooms.iter().map(|r| r.rect.center()).collect();
+
+    for (a, b) in planner::plan(&centers, config.neighbours, config.loop_factor, &mut rng) {
+        dungeon.connect_rooms(RoomId(a), RoomId(b));
+    }
+
+    Ok(())
+}
+
+fn add_room(dungeon: &mut Dungeon, id: usize, prefab: Option<usize>) -> Result<(), GenerationError> {
+    if let Some(index) = prefab {
+        let prefab = dungeon.prefabs[index].clone();
+
+        return place_room(dungeon, id, prefab.size(), Shape::Prefab(prefab));
+    }
+
+    let signed_size = Vector {
+        x: i32::try_from(random::gen_range_inclusive(&mut dungeon.rng, dungeon.min_size.x..=dungeon.max_size.x))
+            .map_err(|_| GenerationError::CoordinateOverflow)?,
+        y: i32::try_from(random::gen_range_inclusive(&mut dungeon.rng, dungeon.min_size.y..=dungeon.max_size.y))
+            .map_err(|_| GenerationError::CoordinateOverflow)?,
+    };
+    // rooms are always aligned on odd sizes
+    let bounds_size = Vector { x: signed_size.x | 1, y: signed_size.y | 1 };
+    let kind = dungeon.shapes.pick(&mut dungeon.rng);
+    let shape = Shape::build(kind, &bounds_size, &mut dungeon.rng);
+
+    place_room(dungeon, id, signed_size, shape)
+}
+
+/// Put the first room at the origin, the other ones next to a random room
+fn place_room(dungeon: &mut Dungeon, id: usize, signed_size: Vector<i32>, shape: Shape) -> Result<(), GenerationError> {
+    let rect = if id == 0 {
+        let mut p2 = signed_size;
+        // align the point to odd cells on grid
+        p2.x = if p2.x % 2 == 0 { p2.x + 1 } else { p2.x };
+        p2.y = if p2.y % 2 == 0 { p2.y + 1 } else { p2.y };
+
+        Rectangle {
+            p1: Vector { x: 0, y: 0 },
+            p2,
+        }
+    } else {
+        dungeon
+            .find_empty_space(signed_size, &shape)
+            .map_err(|_| GenerationError::PlacementExhausted { room: id })?
+    };
+
+    dungeon.add_room(rect, shape);
+
+    Ok(())
+}
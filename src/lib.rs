@@ -1,64 +1,321 @@
 mod generator;
 
 use generator::map::Map;
-use generator::{math::Vector, run};
-use libc::{c_char, c_uchar};
-use std::ffi::CString;
+use generator::run;
+use libc::{c_char, c_int, c_uchar};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::path::Path;
+use std::ptr;
 
-#[repr(C)]
+pub use generator::analysis::DungeonStats;
+pub use generator::distance::{DistanceMap, TileCosts};
+pub use generator::errors::{ConfigError, GenerationError};
+pub use generator::export::{render, to_json, to_json_line, to_svg, to_tmj, to_tmx, Image, ImageTooBig, Palette, RenderOptions, Rgb, SvgOptions, TileIds, TiledOptions, MAX_PIXELS};
+pub use generator::layout::{ConnectionLayout, DungeonLayout, PrefabLayout, Repair, RoomLayout};
+pub use generator::map::{Neighbourhood, TileKind};
+pub use generator::math::{Direction, Rectangle, Vector};
+pub use generator::prefab::Prefab;
+pub use generator::presets::PRESETS;
+pub use generator::shape::{RoomShape, ShapeWeights};
+pub use generator::router::{AStarRouter, CorridorRouter, Route, RotationRouter, Router};
+pub use generator::spatial::SpatialIndex;
+pub use generator::strategy::{CaveRules, Pockets, Strategy};
+
+/// Opaque to C, created by `map_create` and released by `map_destroy`
 pub struct Handle {
     _data: Map,
+    _layout: DungeonLayout,
+    _config: Config,
+    /// Computed by the first call to `map_stats` or `map_room_doors`
+    _stats: Option<DungeonStats>,
+}
+
+impl Handle {
+    fn stats(&mut self) -> &DungeonStats {
+        self._stats.get_or_insert_with(|| DungeonStats::new(&self._data, &self._layout, &self._config))
+    }
 }
 
+/// Settings of the generation. Serialised fields use the names of the
+/// struct, the missing ones keep their `Config::default` value when loading a
+/// file.
+///
+/// C writes the enums as `u32` codes, checked by `validate` and read back
+/// with the getters of the same name.
 #[repr(C)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub seed: u64,
     pub rooms_count: usize,
-    pub rooms_min_size: Vector<u8>,
-    pub rooms_max_size: Vector<u8>,
+    pub rooms_min_size: Vector<u32>,
+    pub rooms_max_size: Vector<u32>,
     pub rooms_spacing: MinMax,
     pub path_extension: MinMax,
+    /// Code of a `Neighbourhood`
+    #[serde(serialize_with = "code::serialize::<Neighbourhood, _>", deserialize_with = "code::deserialize::<Neighbourhood, _>")]
+    pub walls: u32,
+    /// Code of a `Router`
+    #[serde(serialize_with = "code::serialize::<Router, _>", deserialize_with = "code::deserialize::<Router, _>")]
+    pub router: u32,
+    pub turn_penalty: u32,
+    /// Code of a `Strategy`
+    #[serde(serialize_with = "code::serialize::<Strategy, _>", deserialize_with = "code::deserialize::<Strategy, _>")]
+    pub strategy: u32,
+    /// Area covered by the `Bsp` & `Cave` strategies, ignored by `Scatter`
+    pub bounds: Vector<u32>,
+    /// Used by the `Cave` strategy only
+    pub cave: CaveRules,
+    /// Chances of each room shape, ignored by the `Cave` strategy
+    pub shapes: ShapeWeights,
+    /// Number of nearest rooms considered for the extra connections of the
+    /// `Scatter` strategy
+    pub neighbours: u32,
+    /// Percentage of these extra connections added to the spanning tree of
+    /// the rooms, making loops
+    pub loop_factor: u32,
 }
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MinMax {
-    pub min: u8,
-    pub max: u8,
+    pub min: u32,
+    pub max: u32,
+}
+
+/// C view of a `ConnectionLayout`, waypoints are fetched one by one with
+/// `map_connection_waypoint`
+#[repr(C)]
+pub struct ConnectionInfo {
+    pub id: usize,
+    pub from: usize,
+    pub to: usize,
+    pub from_door: Vector<i32>,
+    pub to_door: Vector<i32>,
+    pub waypoints_count: usize,
+}
+
+/// C view of a `DungeonStats`, the doors of each room are fetched one by one
+/// with `map_room_doors`
+#[repr(C)]
+#[derive(Default)]
+pub struct StatsInfo {
+    pub width: u32,
+    pub height: u32,
+    pub rooms: usize,
+    pub requested_rooms: usize,
+    pub connections: usize,
+    pub repairs: usize,
+    pub floor_tiles: usize,
+    pub corridor_tiles: usize,
+    pub door_tiles: usize,
+    pub floor_ratio: f64,
+    pub corridor_ratio: f64,
+    pub empty_ratio: f64,
+    pub utilisation: f64,
+    pub dead_ends: usize,
+    pub leaf_rooms: usize,
+    pub cycles: usize,
+    pub average_corridor_length: f64,
+    pub max_corridor_length: usize,
+    pub average_room_distance: f64,
+    pub max_room_distance: usize,
+    pub corridor_overlaps: usize,
 }
 
 impl Config {
+    /// Positional shorthand for `ConfigBuilder`, `min` & `max` are the
+    /// width and height of the rooms
     pub fn build(
         seed: u64,
         rooms_count: usize,
-        min: Vec<u8>,
-        max: Vec<u8>,
-        spacing: (u8, u8),
-        extension: (u8, u8),
-    ) -> Result<Config, &'static str> {
-        let min = Vector {
-            x: min[0],
-            y: min[1],
-        };
-        let max = Vector {
-            x: max[0],
-            y: max[1],
+        min: Vec<u32>,
+        max: Vec<u32>,
+        spacing: (u32, u32),
+        extension: (u32, u32),
+    ) -> Result<Config, GenerationError> {
+        let size = |v: &[u32]| (v.first().copied().unwrap_or(0), v.get(1).copied().unwrap_or(0));
+        let (min, max) = (size(&min), size(&max));
+
+        let config = Config::builder()
+            .seed(seed)
+            .rooms_count(rooms_count)
+            .rooms_min_size(min.0, min.1)
+            .rooms_max_size(max.0, max.1)
+            .rooms_spacing(spacing.0, spacing.1)
+            .path_extension(extension.0, extension.1)
+            .build()?;
+
+        Ok(config)
+    }
+
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Config of a built-in style of dungeon, among `PRESETS`:
+    /// - `tight-crypt`: many small rooms packed together, few loops
+    /// - `sprawling-caves`: wide caverns joined by tunnels
+    /// - `arena`: a few big halls spread evenly, with many loops
+    /// - `labyrinth`: small rooms far apart, long winding corridors
+    ///
+    /// The seed is the default one, the values can be changed before the
+    /// generation, e.g. with `ConfigBuilder::from`.
+    pub fn preset(name: &str) -> Result<Config, GenerationError> {
+        generator::presets::preset(name)
+            .ok_or_else(|| GenerationError::InvalidConfig(format!("Unknown preset {}, expected one of {}", name, PRESETS.join(", "))))
+    }
+
+    /// Read a config from a TOML file, or a JSON file when its extension is
+    /// `.json`. The config is not validated.
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, GenerationError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| GenerationError::InvalidConfig(format!("Cannot read config {}: {}", path.display(), e)))?;
+
+        let config = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => parse_json(&text),
+            _ => parse_toml(&text),
         };
 
-        if min.x > max.x || min.y > max.y {
-            return Err("Min size cannot be bigger than max size");
+        config.map_err(|e| GenerationError::InvalidConfig(format!("Cannot load config {}: {}", path.display(), e)))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Config, GenerationError> {
+        parse_toml(text).map_err(GenerationError::InvalidConfig)
+    }
+
+    pub fn from_json(text: &str) -> Result<Config, GenerationError> {
+        parse_json(text).map_err(GenerationError::InvalidConfig)
+    }
+
+    /// TOML integers are signed, seeds above `i64::MAX` cannot be written
+    pub fn to_toml(&self) -> Result<String, GenerationError> {
+        toml::to_string(self).map_err(|e| GenerationError::InvalidConfig(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> String {
+        // only numbers & strings, nothing which can fail to serialise
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn walls(&self) -> Result<Neighbourhood, ConfigError> {
+        Neighbourhood::try_from(self.walls)
+    }
+
+    pub fn router(&self) -> Result<Router, ConfigError> {
+        Router::try_from(self.router)
+    }
+
+    pub fn strategy(&self) -> Result<Strategy, ConfigError> {
+        Strategy::try_from(self.strategy)
+    }
+
+    /// Make sure the values won't make the generation panic, see `validate`
+    pub fn check(&self) -> Result<(), GenerationError> {
+        self.validate().map_err(GenerationError::from)
+    }
+
+    /// List every value which would make the generation panic or fail
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let strategy = self.strategy().ok();
+        let rooms = strategy != Some(Strategy::Cave);
+
+        for code in [self.walls().err(), self.router().err(), self.strategy().err(), self.cave.pockets().err()] {
+            errors.extend(code);
+        }
+
+        if rooms && self.rooms_count == 0 {
+            errors.push(ConfigError::Zero { field: "rooms_count" });
+        }
+
+        let sizes = [
+            ("rooms_size.x", self.rooms_min_size.x, self.rooms_max_size.x),
+            ("rooms_size.y", self.rooms_min_size.y, self.rooms_max_size.y),
+        ];
+
+        for (field, min, max) in sizes {
+            if min > max {
+                errors.push(ConfigError::MinAboveMax { field, min, max });
+            }
+        }
+
+        // exits are picked in `p1 + 1..p2 - 2`, away from the corners, this
+        // range is empty for smaller rooms
+        for (field, value) in [("rooms_min_size.x", self.rooms_min_size.x), ("rooms_min_size.y", self.rooms_min_size.y)] {
+            if value < 4 {
+                errors.push(ConfigError::RoomTooSmall { field, value, min: 4 });
+            }
+        }
+
+        // sizes & spacings of any parity are fine: every strategy aligns the
+        // rooms itself, from an even cell to an odd one, which rounds them
+
+        // corridors & doors lie on even cells, both need steps of 2 cells. The
+        // astar router only walks on even cells, it needs one between two
+        // rooms to go round them.
+        let spacing = if self.router() == Ok(Router::AStar) { 3 } else { 2 };
+        let ranges = [
+            ("rooms_spacing", "rooms_spacing.min", self.rooms_spacing, spacing),
+            ("path_extension", "path_extension.min", self.path_extension, 2),
+        ];
+
+        for (field, min_field, range, min) in ranges {
+            if range.min < min {
+                errors.push(ConfigError::TooSmall { field: min_field, value: range.min, min });
+            }
+
+            if range.min >= range.max {
+                errors.push(ConfigError::EmptyRange { field, min: range.min, max: range.max });
+            }
+        }
+
+        if strategy == Some(Strategy::Bsp) {
+            let bounds = [
+                ("bounds.x", self.bounds.x, self.rooms_min_size.x),
+                ("bounds.y", self.bounds.y, self.rooms_min_size.y),
+            ];
+
+            for (field, value, size) in bounds {
+                if value < size + 4 {
+                    errors.push(ConfigError::TooSmall { field, value, min: size + 4 });
+                }
+            }
+        }
+
+        if rooms && self.shapes.total() == 0 {
+            errors.push(ConfigError::Zero { field: "shapes" });
+        }
+
+        if strategy == Some(Strategy::Cave) {
+            let rules = [
+                ("cave.fill", self.cave.fill, 100),
+                ("cave.birth", self.cave.birth, 8),
+                ("cave.survival", self.cave.survival, 8),
+            ];
+
+            for (field, value, max) in rules {
+                if value > max {
+                    errors.push(ConfigError::TooBig { field, value, max });
+                }
+            }
         }
 
-        Ok(Config {
-            seed,
-            rooms_count,
-            rooms_min_size: min,
-            rooms_max_size: max,
-            rooms_spacing: MinMax { min: spacing.0, max: spacing.1 },
-            path_extension: MinMax { min: extension.0, max: extension.1 },
-        })
+        if self.loop_factor > 100 {
+            errors.push(ConfigError::TooBig { field: "loop_factor", value: self.loop_factor, max: 100 });
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
     }
 
+    /// Config with empty sizes & ranges, to be filled before the generation,
+    /// see `Config::default` for usable values
     pub fn new() -> Config {
         Config {
             seed: 0,
@@ -67,33 +324,293 @@ impl Config {
             rooms_max_size: Vector { x: 0, y: 0 },
             rooms_spacing: MinMax { min: 0, max: 0 },
             path_extension: MinMax { min: 0, max: 0 },
+            walls: Neighbourhood::Eight.into(),
+            router: Router::Rotation.into(),
+            turn_penalty: 4,
+            strategy: Strategy::Scatter.into(),
+            bounds: Vector { x: 64, y: 48 },
+            cave: CaveRules::default(),
+            shapes: ShapeWeights::default(),
+            neighbours: 3,
+            loop_factor: 15,
         }
     }
 }
 
+impl Default for Config {
+    /// Defaults of the CLI: 11 rooms from 4x4 to 7x7, spaced by 3 to 5
+    /// cells, corridors extended by 2 to 4 cells
+    fn default() -> Self {
+        Config {
+            seed: 42,
+            rooms_count: 11,
+            rooms_min_size: Vector { x: 4, y: 4 },
+            rooms_max_size: Vector { x: 7, y: 7 },
+            rooms_spacing: MinMax { min: 3, max: 5 },
+            path_extension: MinMax { min: 2, max: 4 },
+            ..Config::new()
+        }
+    }
+}
+
+fn parse_toml(text: &str) -> Result<Config, String> {
+    toml::from_str(text).map_err(|e| match e.span() {
+        Some(span) => format!("line {}: {}", text[..span.start].matches('\n').count() + 1, e.message()),
+        None => e.message().to_string(),
+    })
+}
+
+fn parse_json(text: &str) -> Result<Config, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+/// Enum codes of the config written by the name of their variant, the unknown
+/// ones as numbers so that the config can still be dumped
+mod code {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<E, S>(code: &u32, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        E: TryFrom<u32> + Serialize,
+        S: Serializer,
+    {
+        match E::try_from(*code) {
+            Ok(value) => value.serialize(serializer),
+            Err(_) => serializer.serialize_u32(*code),
+        }
+    }
+
+    pub fn deserialize<'de, E, D>(deserializer: D) -> Result<u32, D::Error>
+    where
+        E: Deserialize<'de> + Into<u32>,
+        D: Deserializer<'de>,
+    {
+        E::deserialize(deserializer).map(Into::into)
+    }
+}
+
+/// Config built with named setters, starting from `Config::default`
+#[derive(Clone, Debug)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+/// Start from an existing config, e.g. loaded from a file
+impl From<Config> for ConfigBuilder {
+    fn from(config: Config) -> Self {
+        ConfigBuilder { config }
+    }
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        ConfigBuilder::new()
+    }
+}
+
+impl ConfigBuilder {
+    pub fn new() -> ConfigBuilder {
+        ConfigBuilder { config: Config::default() }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn rooms_count(mut self, count: usize) -> Self {
+        self.config.rooms_count = count;
+        self
+    }
+
+    pub fn rooms_min_size(mut self, width: u32, height: u32) -> Self {
+        self.config.rooms_min_size = Vector { x: width, y: height };
+        self
+    }
+
+    pub fn rooms_max_size(mut self, width: u32, height: u32) -> Self {
+        self.config.rooms_max_size = Vector { x: width, y: height };
+        self
+    }
+
+    /// Cells between two rooms, picked in `min..max`
+    pub fn rooms_spacing(mut self, min: u32, max: u32) -> Self {
+        self.config.rooms_spacing = MinMax { min, max };
+        self
+    }
+
+    /// Cells added to a corridor when it turns, picked in `min..max`
+    pub fn path_extension(mut self, min: u32, max: u32) -> Self {
+        self.config.path_extension = MinMax { min, max };
+        self
+    }
+
+    pub fn walls(mut self, walls: Neighbourhood) -> Self {
+        self.config.walls = walls.into();
+        self
+    }
+
+    pub fn router(mut self, router: Router) -> Self {
+        self.config.router = router.into();
+        self
+    }
+
+    pub fn turn_penalty(mut self, penalty: u32) -> Self {
+        self.config.turn_penalty = penalty;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.config.strategy = strategy.into();
+        self
+    }
+
+    pub fn bounds(mut self, width: u32, height: u32) -> Self {
+        self.config.bounds = Vector { x: width, y: height };
+        self
+    }
+
+    pub fn cave(mut self, rules: CaveRules) -> Self {
+        self.config.cave = rules;
+        self
+    }
+
+    pub fn shapes(mut self, weights: ShapeWeights) -> Self {
+        self.config.shapes = weights;
+        self
+    }
+
+    pub fn neighbours(mut self, neighbours: u32) -> Self {
+        self.config.neighbours = neighbours;
+        self
+    }
+
+    pub fn loop_factor(mut self, percentage: u32) -> Self {
+        self.config.loop_factor = percentage;
+        self
+    }
+
+    /// Returns every problem found by `Config::validate`
+    pub fn build(self) -> Result<Config, Vec<ConfigError>> {
+        self.config.validate()?;
+
+        Ok(self.config)
+    }
+}
+
+/// Version of the C ABI, bumped on every incompatible change of the
+/// functions or the types shared with C
+pub const DUNGEON_GENERATOR_ABI_VERSION: u32 = 1;
+
+/// Version of the C ABI of the linked lib, to be compared with the
+/// `DUNGEON_GENERATOR_ABI_VERSION` of the header the program was built with
+#[no_mangle]
+pub extern "C" fn abi_version() -> u32 {
+    DUNGEON_GENERATOR_ABI_VERSION
+}
+
+/// Config with empty sizes & ranges (see `Config::new`), to be filled then
+/// passed to `map_create` or released with `config_free`.
 #[no_mangle]
 pub extern "C" fn get_config() -> *mut Config {
     Box::into_raw(Box::new(Config::new()))
 }
 
+/// Config of a built-in preset (see `Config::preset`), to be changed then
+/// passed to `map_create` or released with `config_free`. Returns NULL if
+/// `name` is not a preset, see `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// `name` must be either null or a valid NUL terminated string.
 #[no_mangle]
-pub extern "C" fn map_create(config: *mut Config) -> *mut Handle {
-    let cfg = unsafe { Box::<Config>::from_raw(config) };
+pub unsafe extern "C" fn config_from_preset(name: *const c_char) -> *mut Config {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
 
-    let c = Config::build(
-        cfg.seed,
-        cfg.rooms_count,
-        Vec::from([cfg.rooms_min_size.x, cfg.rooms_min_size.y]),
-        Vec::from([cfg.rooms_max_size.x, cfg.rooms_max_size.y]),
-        (cfg.rooms_spacing.min, cfg.rooms_spacing.max),
-        (cfg.path_extension.min, cfg.path_extension.max),
-    );
+    let name = if name.is_null() { None } else { CStr::from_ptr(name).to_str().ok() };
 
-    drop(cfg);
+    match name.ok_or(GenerationError::InvalidConfig("Preset name is not a valid string".to_string())).and_then(Config::preset) {
+        Ok(config) => Box::into_raw(Box::new(config)),
+        Err(error) => {
+            set_last_error(error);
 
-    Box::into_raw(Box::new(Handle { _data: generate(c.unwrap()) }))
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Release a config which has not been passed to `map_create`.
+///
+/// # Safety
+///
+/// `config` must be either null or a pointer obtained from `get_config`, it
+/// must not be used afterward.
+#[no_mangle]
+pub unsafe extern "C" fn config_free(config: *mut Config) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(c_int, CString)>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: GenerationError) {
+    let message = CString::new(error.to_string()).unwrap_or_default();
+
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((error.code(), message)));
+}
+
+/// Returns NULL if the generation fails, see `last_error_code` and
+/// `last_error_message` to know why.
+///
+/// # Safety
+///
+/// `config` must be a pointer obtained from `get_config`, it is consumed by
+/// this function and must not be used afterward.
+#[no_mangle]
+pub unsafe extern "C" fn map_create(config: *mut Config) -> *mut Handle {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+
+    if config.is_null() {
+        set_last_error(GenerationError::InvalidConfig("Config is NULL".to_string()));
+
+        return ptr::null_mut();
+    }
+
+    let config = *Box::<Config>::from_raw(config);
+    let c = config.check().map(|_| config);
+
+    match c.and_then(|c| generate(c.clone()).map(|(map, layout)| (map, layout, c))) {
+        Ok((map, layout, config)) => Box::into_raw(Box::new(Handle { _data: map, _layout: layout, _config: config, _stats: None })),
+        Err(error) => {
+            set_last_error(error);
+
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Code of the error which made the last `map_create` or
+/// `config_from_preset` call of the thread fail, 0 if it succeeded
+#[no_mangle]
+pub extern "C" fn last_error_code() -> c_int {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(0, |(code, _)| *code))
+}
+
+/// Message of the error which made the last `map_create` or
+/// `config_from_preset` call of the thread fail, NULL if it succeeded. The
+/// string is owned by the library and stays valid until the next call to
+/// either function on the same thread.
+#[no_mangle]
+pub extern "C" fn last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |(_, message)| message.as_ptr()))
 }
 
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
 #[no_mangle]
 pub unsafe extern "C" fn map_destroy(handle: *mut Handle) -> std::os::raw::c_int {
     if !handle.is_null() {
@@ -105,8 +622,11 @@ pub unsafe extern "C" fn map_destroy(handle: *mut Handle) -> std::os::raw::c_int
     -1
 }
 
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
 #[no_mangle]
-pub unsafe extern "C" fn map_size(handle: *mut Handle) -> Vector<u8> {
+pub unsafe extern "C" fn map_size(handle: *mut Handle) -> Vector<u32> {
     if let Some(handle) = handle.as_mut() {
         return Vector { x: handle._data.width, y: handle._data.height };
     }
@@ -114,38 +634,376 @@ pub unsafe extern "C" fn map_size(handle: *mut Handle) -> Vector<u8> {
     Vector { x: 0, y: 0 }
 }
 
+/// ASCII grid of the map, NULL if `handle` is NULL. The string is owned by
+/// the caller and must be released with `map_string_free`.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
 #[no_mangle]
 pub unsafe extern "C" fn map_as_string(handle: *mut Handle) -> *mut c_char {
     if let Some(handle) = handle.as_mut() {
+        // the glyphs of the tiles are never NUL
         let c_str_grid = CString::new(handle._data.to_ascii()).unwrap();
 
         return c_str_grid.into_raw();
     }
 
-    CString::new("").unwrap().into_raw()
+    ptr::null_mut()
+}
+
+/// JSON document with the map, its layout and the config it was generated
+/// with, see `to_json`. NULL if `handle` is NULL. The string is owned by the
+/// caller and must be released with `map_string_free`.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_as_json(handle: *mut Handle) -> *mut c_char {
+    if let Some(handle) = handle.as_mut() {
+        // JSON escapes the control characters, NUL included
+        let json = to_json(&handle._data, &handle._layout, &handle._config);
+
+        return CString::new(json).unwrap().into_raw();
+    }
+
+    ptr::null_mut()
+}
+
+/// Release a string returned by `map_as_string` or `map_as_json`.
+///
+/// # Safety
+///
+/// `string` must be either null or a pointer returned by `map_as_string` or
+/// `map_as_json`, it must not be used afterward.
+#[no_mangle]
+pub unsafe extern "C" fn map_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Tiles of the map, one byte per tile, row after row. The number of bytes
+/// is written to `length`. NULL, with a length of 0, if `handle` is NULL.
+/// The bytes are owned by the caller and must be released with
+/// `map_bytes_free`.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`,
+/// `length` must be either null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn map_as_bytes(handle: *mut Handle, length: *mut usize) -> *mut c_uchar {
+    let bytes = handle.as_mut().map(|handle| handle._data.to_bytes().into_boxed_slice());
+
+    if let Some(length) = length.as_mut() {
+        *length = bytes.as_ref().map_or(0, |bytes| bytes.len());
+    }
+
+    match bytes {
+        Some(bytes) => Box::into_raw(bytes) as *mut c_uchar,
+        None => ptr::null_mut(),
+    }
+}
+
+/// Copy the tiles of the map, one byte per tile, row after row, into a
+/// buffer of `size` bytes owned by the caller. Returns the number of bytes of
+/// the map, nothing is copied if it is bigger than `size`. Returns 0 if
+/// `handle` is NULL.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`,
+/// `buffer` must be either null or valid for writes of `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn map_copy_bytes(handle: *mut Handle, buffer: *mut c_uchar, size: usize) -> usize {
+    if let Some(handle) = handle.as_mut() {
+        let bytes = handle._data.to_bytes();
+
+        if !buffer.is_null() && bytes.len() <= size {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+        }
+
+        return bytes.len();
+    }
+
+    0
+}
+
+/// Release the bytes returned by `map_as_bytes`.
+///
+/// # Safety
+///
+/// `bytes` must be either null or a pointer returned by `map_as_bytes`, with
+/// the `length` it returned, it must not be used afterward.
+#[no_mangle]
+pub unsafe extern "C" fn map_bytes_free(bytes: *mut c_uchar, length: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, length)));
+    }
+}
+
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_rooms_count(handle: *mut Handle) -> usize {
+    if let Some(handle) = handle.as_mut() {
+        return handle._layout.rooms.len();
+    }
+
+    0
+}
+
+/// Returns a room with an id of `usize::MAX` if `index` is out of bounds.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_room(handle: *mut Handle, index: usize) -> RoomLayout {
+    if let Some(room) = handle.as_mut().and_then(|h| h._layout.rooms.get(index)) {
+        return room.clone();
+    }
+
+    RoomLayout {
+        id: usize::MAX,
+        bounds: Rectangle { p1: Vector { x: 0, y: 0 }, p2: Vector { x: 0, y: 0 } },
+        shape: RoomShape::Rectangle,
+    }
 }
 
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
 #[no_mangle]
-pub unsafe extern "C" fn map_as_bytes(handle: *mut Handle) -> *mut c_uchar {
+pub unsafe extern "C" fn map_connections_count(handle: *mut Handle) -> usize {
     if let Some(handle) = handle.as_mut() {
-        let mut vec = handle._data.to_bytes();
-        let ptr = vec.as_mut_ptr();
+        return handle._layout.connections.len();
+    }
+
+    0
+}
+
+/// Returns a connection with an id of `usize::MAX` if `index` is out of bounds.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_connection(handle: *mut Handle, index: usize) -> ConnectionInfo {
+    if let Some(connection) = handle.as_mut().and_then(|h| h._layout.connections.get(index)) {
+        return ConnectionInfo {
+            id: connection.id,
+            from: connection.from,
+            to: connection.to,
+            from_door: connection.from_door.clone(),
+            to_door: connection.to_door.clone(),
+            waypoints_count: connection.waypoints.len(),
+        };
+    }
+
+    ConnectionInfo {
+        id: usize::MAX,
+        from: usize::MAX,
+        to: usize::MAX,
+        from_door: Vector { x: 0, y: 0 },
+        to_door: Vector { x: 0, y: 0 },
+        waypoints_count: 0,
+    }
+}
+
+/// Returns (0, 0) if either `index` or `waypoint` is out of bounds.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_connection_waypoint(handle: *mut Handle, index: usize, waypoint: usize) -> Vector<i32> {
+    if let Some(connection) = handle.as_mut().and_then(|h| h._layout.connections.get(index)) {
+        if let Some(position) = connection.waypoints.get(waypoint) {
+            return position.clone();
+        }
+    }
+
+    Vector { x: 0, y: 0 }
+}
+
+/// Quality figures of the map, all zero if `handle` is NULL.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_stats(handle: *mut Handle) -> StatsInfo {
+    let stats = match handle.as_mut() {
+        Some(handle) => handle.stats(),
+        None => return StatsInfo::default(),
+    };
 
-        std::mem::forget(vec);
+    StatsInfo {
+        width: stats.width,
+        height: stats.height,
+        rooms: stats.rooms,
+        requested_rooms: stats.requested_rooms,
+        connections: stats.connections,
+        repairs: stats.repairs,
+        floor_tiles: stats.floor_tiles,
+        corridor_tiles: stats.corridor_tiles,
+        door_tiles: stats.door_tiles,
+        floor_ratio: stats.floor_ratio,
+        corridor_ratio: stats.corridor_ratio,
+        empty_ratio: stats.empty_ratio,
+        utilisation: stats.utilisation,
+        dead_ends: stats.dead_ends,
+        leaf_rooms: stats.leaf_rooms,
+        cycles: stats.cycles,
+        average_corridor_length: stats.average_corridor_length,
+        max_corridor_length: stats.max_corridor_length,
+        average_room_distance: stats.average_room_distance,
+        max_room_distance: stats.max_room_distance,
+        corridor_overlaps: stats.corridor_overlaps,
+    }
+}
 
-        return ptr;
+/// Doors of the room at `index`, as listed by `map_room`. Returns 0 if
+/// `index` is out of bounds.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`.
+#[no_mangle]
+pub unsafe extern "C" fn map_room_doors(handle: *mut Handle, index: usize) -> usize {
+    if let Some(handle) = handle.as_mut() {
+        return handle.stats().doors_per_room.get(index).copied().unwrap_or(0);
     }
 
-    Box::into_raw(Box::new(Vec::<u8>::new())) as *mut _
+    0
 }
 
-pub fn generate(config: Config) -> Map {
-    run(
-        config.seed,
-        config.rooms_count,
-        config.rooms_min_size,
-        config.rooms_max_size,
-        (config.rooms_spacing.min, config.rooms_spacing.max),
+/// Distance of the tiles which cannot be walked to in the grids of
+/// `map_copy_distances`
+pub const DISTANCE_UNREACHABLE: u32 = u32::MAX;
+
+/// Copy the walking distance from the nearest of the `sources` (`count`
+/// positions, in map coordinates) to each tile of the map, row after row,
+/// into a buffer of `size` values owned by the caller. Tiles which cannot be
+/// walked to are `DISTANCE_UNREACHABLE`. Returns the number of tiles of the
+/// map, nothing is copied if it is bigger than `size`. Returns 0 if `handle`
+/// is NULL.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`,
+/// `sources` must be either null or valid for reads of `count` positions and
+/// `buffer` either null or valid for writes of `size` values.
+#[no_mangle]
+pub unsafe extern "C" fn map_copy_distances(handle: *mut Handle, sources: *const Vector<i32>, count: usize, costs: TileCosts, buffer: *mut u32, size: usize) -> usize {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return 0,
+    };
+    let sources = if sources.is_null() { &[] } else { std::slice::from_raw_parts(sources, count) };
+    let distances = handle._data.distance_map_with_costs(sources, &costs);
+    let distances = distances.distances();
+
+    if !buffer.is_null() && distances.len() <= size {
+        for (n, distance) in distances.iter().enumerate() {
+            *buffer.add(n) = distance.unwrap_or(DISTANCE_UNREACHABLE);
+        }
+    }
+
+    distances.len()
+}
+
+/// Id of the room the farthest from the room `start` (an id), walking one
+/// tile at a time, and its distance in `distance` if not NULL. Returns
+/// `usize::MAX` if there is no room `start` or if `handle` is NULL.
+///
+/// # Safety
+///
+/// `handle` must be either null or a pointer returned by `map_create`,
+/// `distance` must be either null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn map_farthest_room(handle: *mut Handle, start: usize, distance: *mut u32) -> usize {
+    let farthest = handle.as_mut().and_then(|h| h._data.farthest_room(&h._layout, start));
+
+    match farthest {
+        Some((room, d)) => {
+            if let Some(distance) = distance.as_mut() {
+                *distance = d;
+            }
+
+            room
+        }
+        None => usize::MAX,
+    }
+}
+
+pub fn generate(config: Config) -> Result<(Map, DungeonLayout), GenerationError> {
+    generate_with_prefabs(config, &[])
+}
+
+/// Generate a dungeon tracing the corridors with a custom router, the
+/// `router` & `turn_penalty` fields of the config are ignored
+pub fn generate_with_router(config: Config, router: Box<dyn CorridorRouter>) -> Result<(Map, DungeonLayout), GenerationError> {
+    run(&config, router, &[])
+}
+
+/// Generate a dungeon mixing the prefabs with the procedural rooms, the
+/// prefabs are ignored by the `Cave` strategy
+pub fn generate_with_prefabs(config: Config, prefabs: &[Prefab]) -> Result<(Map, DungeonLayout), GenerationError> {
+    let router = config.router()?.build(
         (config.path_extension.min, config.path_extension.max),
-    )
+        config.turn_penalty,
+    );
+
+    run(&config, router, prefabs)
+}
+
+/// Seed of a batch, with its dungeon or the error which stopped its
+/// generation
+pub type BatchResult = (u64, Result<(Map, DungeonLayout), GenerationError>);
+
+/// Generate one dungeon per seed, in parallel over the threads of the rayon
+/// pool. The seed of the config is ignored, the results are in the order of
+/// the seeds.
+pub fn generate_batch(config: &Config, seeds: &[u64]) -> Vec<BatchResult> {
+    generate_batch_with_prefabs(config, &[], seeds)
+}
+
+/// `generate_batch` mixing the prefabs with the procedural rooms
+pub fn generate_batch_with_prefabs(config: &Config, prefabs: &[Prefab], seeds: &[u64]) -> Vec<BatchResult> {
+    seeds.par_iter()
+        .map(|seed| (*seed, generate_with_prefabs(Config { seed: *seed, ..config.clone() }, prefabs)))
+        .collect()
+}
+
+/// `generate_batch` yielding the results as they are done: the seeds are
+/// generated `chunk` at a time, in parallel, when the previous results have
+/// been consumed. Only one chunk of dungeons is held in memory.
+pub fn generate_batch_iter<'a, I>(config: &'a Config, seeds: I, chunk: usize) -> impl Iterator<Item = BatchResult> + 'a
+where
+    I: IntoIterator<Item = u64>,
+    I::IntoIter: 'a,
+{
+    generate_batch_iter_with_prefabs(config, &[], seeds, chunk)
+}
+
+/// `generate_batch_iter` mixing the prefabs with the procedural rooms
+pub fn generate_batch_iter_with_prefabs<'a, I>(config: &'a Config, prefabs: &'a [Prefab], seeds: I, chunk: usize) -> impl Iterator<Item = BatchResult> + 'a
+where
+    I: IntoIterator<Item = u64>,
+    I::IntoIter: 'a,
+{
+    let mut seeds = seeds.into_iter();
+    let chunk = chunk.max(1);
+
+    std::iter::from_fn(move || {
+        let seeds: Vec<u64> = seeds.by_ref().take(chunk).collect();
+
+        (!seeds.is_empty()).then(|| generate_batch_with_prefabs(config, prefabs, &seeds))
+    })
+    .flatten()
 }
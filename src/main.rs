@@ -1,13 +1,57 @@
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueSource};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
-use dungeon_generator::{generate, Config};
+use dungeon_generator::{generate_batch_iter_with_prefabs, generate_with_prefabs, render, to_json, to_json_line, to_svg, to_tmj, to_tmx, Config, ConfigBuilder, DungeonStats, GenerationError, Neighbourhood, Palette, Pockets, Prefab, PRESETS, RenderOptions, Repair, Rgb, Router, ShapeWeights, Strategy, SvgOptions, TiledOptions};
+
+/// Seeds of `--seeds` generated at once, the results are written between
+/// two chunks
+const BATCH_CHUNK: usize = 256;
 
 #[derive(Parser)]
 #[clap(version, author)]
 pub struct Args {
+    #[clap(long, help = "TOML config file, or JSON if it ends with .json, the other flags override its values")]
+    config: Option<String>,
+    #[clap(
+        long,
+        conflicts_with = "config",
+        value_parser = PRESETS,
+        help = "Built-in style of dungeon, the other flags override its values"
+    )]
+    preset: Option<String>,
+    #[clap(long, help = "Print the effective config as TOML, or JSON with --format json, and exit")]
+    dump_config: bool,
+    #[clap(
+        long,
+        conflicts_with = "seeds",
+        help = "Print the stats of the map instead of the map, as JSON with --format json"
+    )]
+    stats: bool,
     #[clap(long, default_value = "42", help = "Seed")]
     seed: u64,
+    #[clap(
+        long,
+        value_parser = parse_seeds,
+        conflicts_with_all = &["seed", "dump-config", "render", "svg"],
+        help = "Generate one map per seed of START..END (END excluded) or START..=END, in parallel, and print their stats as JSON lines"
+    )]
+    seeds: Option<(u64, u64)>,
+    #[clap(
+        long,
+        requires = "seeds",
+        help = "Write each map of --seeds to SEED.txt (or .json, .tmx, .tmj following --format) in the directory, and their stats to stats.jsonl"
+    )]
+    out_dir: Option<String>,
+    #[clap(
+        long,
+        requires = "seeds",
+        conflicts_with = "out-dir",
+        help = "Write the maps of --seeds to a JSON-lines file, one JSON document with the stats of the map per line"
+    )]
+    jsonl: Option<String>,
     #[clap(
         short,
         long,
@@ -19,76 +63,663 @@ pub struct Args {
     #[clap(
         long,
         multiple = true, number_of_values = 2,
-        value_parser = clap::value_parser!(u8).range(2..),
+        value_parser = clap::value_parser!(u32).range(2..),
         default_values = &["4", "4"],
         help = "Minimum size of a room"
     )]
-    min: Vec<u8>,
+    min: Vec<u32>,
     #[clap(
         long,
         multiple = true,
         number_of_values = 2,
-        value_parser = clap::value_parser!(u8).range(2..),
+        value_parser = clap::value_parser!(u32).range(2..),
         default_values = &["7", "7"],
         help = "Maximum size of a room"
     )]
-    max: Vec<u8>,
+    max: Vec<u32>,
     #[clap(
         long,
         multiple = true,
         number_of_values = 2,
-        value_parser = clap::value_parser!(u8).range(2..),
+        value_parser = clap::value_parser!(u32).range(2..),
         default_values = &["3", "5"],
         help = "Min & Max spacing between rooms"
     )]
-    spacing: Vec<u8>,
+    spacing: Vec<u32>,
     #[clap(
         long,
         multiple = true,
         number_of_values = 2,
-        value_parser = clap::value_parser!(u8).range(2..),
+        value_parser = clap::value_parser!(u32).range(2..),
         default_values = &["2", "4"],
         help = "Min & Max size when extending a path"
     )]
-    extension: Vec<u8>,
+    extension: Vec<u32>,
+    #[clap(
+        long,
+        default_value = "3",
+        help = "Nearest rooms considered for the extra connections making loops"
+    )]
+    neighbours: u32,
+    #[clap(
+        long,
+        default_value = "15",
+        value_parser = clap::value_parser!(u32).range(0..=100),
+        help = "Percentage of extra connections added to the spanning tree of the rooms"
+    )]
+    loop_factor: u32,
+    #[clap(
+        long,
+        value_parser = ["4", "8"],
+        default_value = "8",
+        help = "Number of neighbours wrapped with walls"
+    )]
+    walls: String,
+    #[clap(
+        long,
+        value_parser = ["rotation", "astar"],
+        default_value = "rotation",
+        help = "Algorithm tracing the corridors"
+    )]
+    router: String,
+    #[clap(
+        long,
+        default_value = "4",
+        help = "Extra cost of a turn for the astar router"
+    )]
+    turn_penalty: u32,
+    #[clap(
+        long,
+        value_parser = ["scatter", "bsp", "cave"],
+        default_value = "scatter",
+        help = "Algorithm placing the rooms"
+    )]
+    strategy: String,
+    #[clap(
+        long,
+        multiple = true,
+        number_of_values = 2,
+        value_parser = clap::value_parser!(u32),
+        default_values = &["64", "48"],
+        help = "Width & Height of the area covered by the bsp & cave strategies"
+    )]
+    bounds: Vec<u32>,
+    #[clap(
+        long,
+        default_value = "45",
+        value_parser = clap::value_parser!(u32).range(0..=100),
+        help = "Percentage of rock before the first cave iteration"
+    )]
+    fill: u32,
+    #[clap(
+        long,
+        default_value = "5",
+        help = "Rock neighbours turning an open cave cell into rock"
+    )]
+    birth: u32,
+    #[clap(
+        long,
+        default_value = "4",
+        help = "Rock neighbours keeping a cave cell filled with rock"
+    )]
+    survival: u32,
+    #[clap(
+        long,
+        default_value = "4",
+        help = "Number of iterations of the cave automaton"
+    )]
+    iterations: u32,
+    #[clap(
+        long,
+        value_parser = ["cull", "join"],
+        default_value = "join",
+        help = "What to do with the caverns apart from the biggest one"
+    )]
+    pockets: String,
+    #[clap(
+        long,
+        default_value = "8",
+        help = "Caverns with fewer cells are filled back with rock"
+    )]
+    min_pocket: u32,
+    #[clap(
+        long,
+        multiple = true,
+        value_parser = parse_shape_weight,
+        default_values = &["rectangle=1"],
+        help = "Weights of the room shapes, among rectangle, ellipse, cross, l, pillars & composite"
+    )]
+    shapes: Vec<(String, u32)>,
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        help = "Prefab template file, mixed with the procedural rooms"
+    )]
+    prefab: Vec<String>,
+    #[clap(
+        long,
+        value_parser = ["text", "json", "tmx", "tmj"],
+        default_value = "text",
+        help = "Output format, tmx & tmj are Tiled maps"
+    )]
+    format: String,
+    #[clap(
+        long,
+        multiple = true,
+        value_parser = parse_tile_id,
+        help = "Tiled tile ids of the tiles, among empty, floor, corridor, door & wall"
+    )]
+    tile_ids: Vec<(String, u32)>,
+    #[clap(
+        long,
+        multiple = true,
+        number_of_values = 2,
+        value_parser = clap::value_parser!(u32).range(1..),
+        default_values = &["16", "16"],
+        help = "Width & Height of the Tiled tiles, in pixels"
+    )]
+    tile_size: Vec<u32>,
+    #[clap(long, help = "External tileset of the Tiled maps")]
+    tileset: Option<String>,
+    #[clap(long, help = "Render the map to an image, PPM if the file ends with .ppm, PNG otherwise")]
+    render: Option<String>,
+    #[clap(
+        long,
+        default_value = "8",
+        value_parser = clap::value_parser!(u32).range(1..=256),
+        help = "Size of a tile in the rendered image & the SVG, in pixels"
+    )]
+    scale: u32,
+    #[clap(long, help = "Outline the tiles in the rendered image")]
+    grid: bool,
+    #[clap(long, help = "Write the room ids in the rendered image")]
+    labels: bool,
+    #[clap(long, help = "Draw the corridor paths in the rendered image")]
+    overlay: bool,
+    #[clap(long, help = "Draw the map, its rooms & its corridors to an SVG file")]
+    svg: Option<String>,
+    #[clap(long, help = "Draw the room graph in the SVG")]
+    graph: bool,
+    #[clap(
+        long,
+        multiple = true,
+        value_parser = parse_color,
+        help = "Colours of the rendered image & the SVG as NAME=RRGGBB, among empty, floor, corridor, door, wall, grid, label, overlay & graph"
+    )]
+    palette: Vec<(String, Rgb)>,
+}
+
+/// First & last seeds of a range
+fn parse_seeds(value: &str) -> Result<(u64, u64), String> {
+    let (start, end, inclusive) = match value.split_once("..=") {
+        Some((start, end)) => (start, end, true),
+        None => {
+            let (start, end) = value.split_once("..").ok_or("expected START..END or START..=END")?;
+
+            (start, end, false)
+        }
+    };
+    let start = start.parse::<u64>().map_err(|e| e.to_string())?;
+    let end = end.parse::<u64>().map_err(|e| e.to_string())?;
+    let last = if inclusive { Some(end) } else { end.checked_sub(1) };
+
+    match last {
+        Some(last) if last >= start => Ok((start, last)),
+        _ => Err(format!("empty range `{}`", value)),
+    }
+}
+
+fn parse_shape_weight(value: &str) -> Result<(String, u32), String> {
+    let (shape, weight) = value.split_once('=').ok_or("expected SHAPE=WEIGHT")?;
+    let weight = weight.parse::<u32>().map_err(|e| e.to_string())?;
+
+    match shape {
+        "rectangle" | "ellipse" | "cross" | "l" | "pillars" | "composite" => Ok((shape.to_string(), weight)),
+        _ => Err(format!("unknown shape `{}`", shape)),
+    }
+}
+
+fn parse_tile_id(value: &str) -> Result<(String, u32), String> {
+    let (tile, id) = value.split_once('=').ok_or("expected TILE=ID")?;
+    let id = id.parse::<u32>().map_err(|e| e.to_string())?;
+
+    match tile {
+        "empty" | "floor" | "corridor" | "door" | "wall" => Ok((tile.to_string(), id)),
+        _ => Err(format!("unknown tile `{}`", tile)),
+    }
+}
+
+fn parse_color(value: &str) -> Result<(String, Rgb), String> {
+    let (name, color) = value.split_once('=').ok_or("expected NAME=RRGGBB")?;
+    let rgb = u32::from_str_radix(color.trim_start_matches('#'), 16)
+        .ok()
+        .filter(|_| color.trim_start_matches('#').len() == 6)
+        .ok_or(format!("invalid colour `{}`", color))?;
+
+    match name {
+        "empty" | "floor" | "corridor" | "door" | "wall" | "grid" | "label" | "overlay" | "graph" => {
+            Ok((name.to_string(), [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]))
+        }
+        _ => Err(format!("unknown colour `{}`", name)),
+    }
 }
 
 fn main() -> ExitCode {
-    let args = Args::parse();
-    let config = Config::build(args.seed,
-                               args.rooms,
-                               args.min,
-                               args.max,
-                               (args.spacing[0], args.spacing[1]),
-                               (args.extension[0], args.extension[1]),
-                            );
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // the flags of the command line override the values of the config file
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
 
-    if let Err(e) = config {
-        println!("Process exited with error: {}", e);
+    let base = match (args.config.as_ref(), args.preset.as_ref()) {
+        (Some(path), _) => Config::load(path),
+        (_, Some(name)) => Config::preset(name),
+        _ => Ok(Config::default()),
+    };
+    let base = match base {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Process exited with error: {}", e);
 
-        return ExitCode::from(101);
+            return ExitCode::from(101);
+        }
+    };
+    let mut cave = base.cave;
+    let mut builder = ConfigBuilder::from(base);
+
+    if given("seed") {
+        builder = builder.seed(args.seed);
+    }
+
+    if given("rooms") {
+        builder = builder.rooms_count(args.rooms);
+    }
+
+    if given("min") {
+        builder = builder.rooms_min_size(args.min[0], args.min[1]);
+    }
+
+    if given("max") {
+        builder = builder.rooms_max_size(args.max[0], args.max[1]);
+    }
+
+    if given("spacing") {
+        builder = builder.rooms_spacing(args.spacing[0], args.spacing[1]);
+    }
+
+    if given("extension") {
+        builder = builder.path_extension(args.extension[0], args.extension[1]);
+    }
+
+    if given("neighbours") {
+        builder = builder.neighbours(args.neighbours);
+    }
+
+    if given("loop-factor") {
+        builder = builder.loop_factor(args.loop_factor);
+    }
+
+    if given("walls") {
+        builder = builder.walls(match args.walls.as_str() {
+            "4" => Neighbourhood::Four,
+            _ => Neighbourhood::Eight,
+        });
+    }
+
+    if given("router") {
+        builder = builder.router(match args.router.as_str() {
+            "astar" => Router::AStar,
+            _ => Router::Rotation,
+        });
+    }
+
+    if given("turn-penalty") {
+        builder = builder.turn_penalty(args.turn_penalty);
+    }
+
+    if given("strategy") {
+        builder = builder.strategy(match args.strategy.as_str() {
+            "bsp" => Strategy::Bsp,
+            "cave" => Strategy::Cave,
+            _ => Strategy::Scatter,
+        });
+    }
+
+    if given("bounds") {
+        builder = builder.bounds(args.bounds[0], args.bounds[1]);
+    }
+
+    let cave_flags = ["fill", "birth", "survival", "iterations", "min-pocket", "pockets"];
+
+    if cave_flags.iter().any(|id| given(id)) {
+        if given("fill") {
+            cave.fill = args.fill;
+        }
+
+        if given("birth") {
+            cave.birth = args.birth;
+        }
+
+        if given("survival") {
+            cave.survival = args.survival;
+        }
+
+        if given("iterations") {
+            cave.iterations = args.iterations;
+        }
+
+        if given("min-pocket") {
+            cave.min_pocket = args.min_pocket;
+        }
+
+        if given("pockets") {
+            cave.pockets = match args.pockets.as_str() {
+                "cull" => Pockets::Cull,
+                _ => Pockets::Join,
+            }
+            .into();
+        }
+
+        builder = builder.cave(cave);
+    }
+
+    if given("shapes") {
+        let mut shapes = ShapeWeights { rectangle: 0, ..ShapeWeights::default() };
+
+        for (shape, weight) in args.shapes.iter() {
+            match shape.as_str() {
+                "rectangle" => shapes.rectangle = *weight,
+                "ellipse" => shapes.ellipse = *weight,
+                "cross" => shapes.cross = *weight,
+                "l" => shapes.l_shape = *weight,
+                "pillars" => shapes.pillars = *weight,
+                _ => shapes.composite = *weight,
+            }
+        }
+
+        builder = builder.shapes(shapes);
+    }
+
+    let config = match builder.build() {
+        Ok(config) => config,
+        Err(errors) => {
+            println!("Process exited with error: {}", GenerationError::from(errors));
+
+            return ExitCode::from(101);
+        }
+    };
+
+    if args.dump_config {
+        let dump = match args.format.as_str() {
+            "json" => Ok(config.to_json()),
+            _ => config.to_toml(),
+        };
+
+        return match dump {
+            Ok(dump) => {
+                println!("{}", dump.trim_end());
+
+                ExitCode::from(0)
+            }
+            Err(e) => {
+                println!("Process exited with error: {}", e);
+
+                ExitCode::from(101)
+            }
+        };
+    }
+
+    if let Some(seeds) = args.seeds {
+        let prefabs: Result<Vec<Prefab>, GenerationError> = args.prefab.iter().map(Prefab::load).collect();
+        let result = prefabs
+            .map_err(|e| e.to_string())
+            .and_then(|prefabs| run_batch(&args, &config, &prefabs, seeds));
+
+        return match result {
+            Ok((generated, failed)) => {
+                eprintln!("Generated {} maps, {} failed", generated, failed);
+
+                ExitCode::from(0)
+            }
+            Err(e) => {
+                println!("Process exited with error: {}", e);
+
+                ExitCode::from(101)
+            }
+        };
     }
 
-    println!("Map seed: {}", args.seed);
-    println!("Number of rooms: {}", args.rooms);
+    let text = args.format == "text";
+
+    // the stats are printed alone, to be parsed
+    if text && !args.stats {
+        println!("Map seed: {}", config.seed);
+        println!("Number of rooms: {}", config.rooms_count);
+    }
+
+    let prefabs: Result<Vec<Prefab>, GenerationError> = args.prefab.iter().map(Prefab::load).collect();
+    let result = prefabs.and_then(|prefabs| generate_with_prefabs(config.clone(), &prefabs));
+
+    let (map, layout) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            println!("Process exited with error: {}", e);
+
+            return ExitCode::from(101);
+        }
+    };
+
+    let mut palette = Palette::default();
+
+    for (name, color) in args.palette.iter() {
+        match name.as_str() {
+            "empty" => palette.empty = *color,
+            "floor" => palette.floor = *color,
+            "corridor" => palette.corridor = *color,
+            "door" => palette.door = *color,
+            "wall" => palette.wall = *color,
+            "grid" => palette.grid = *color,
+            "label" => palette.label = *color,
+            "overlay" => palette.overlay = *color,
+            _ => palette.graph = *color,
+        }
+    }
+
+    if let Some(path) = args.render.as_ref() {
+        let options = RenderOptions {
+            palette,
+            scale: args.scale,
+            grid: args.grid,
+            labels: args.labels,
+            overlay: args.overlay,
+        };
+        let image = match render(&map, &layout, &options) {
+            Ok(image) => image,
+            Err(e) => {
+                println!("Process exited with error: Cannot render {}: {}", path, e);
+
+                return ExitCode::from(101);
+            }
+        };
+        let data = if path.ends_with(".ppm") { image.to_ppm() } else { image.to_png() };
+
+        if let Err(e) = std::fs::write(path, data) {
+            println!("Process exited with error: Cannot write {}: {}", path, e);
+
+            return ExitCode::from(101);
+        }
+    }
+
+    if let Some(path) = args.svg.as_ref() {
+        let options = SvgOptions {
+            palette,
+            scale: args.scale,
+            graph: args.graph,
+        };
+
+        if let Err(e) = std::fs::write(path, to_svg(&map, &layout, &options)) {
+            println!("Process exited with error: Cannot write {}: {}", path, e);
+
+            return ExitCode::from(101);
+        }
+    }
+
+    if args.stats {
+        let stats = DungeonStats::new(&map, &layout, &config);
+
+        match args.format.as_str() {
+            // only numbers, nothing which can fail to serialise
+            "json" => println!("{}", serde_json::to_string_pretty(&stats).unwrap()),
+            _ => print_stats(&stats),
+        }
+
+        return ExitCode::from(0);
+    }
+
+    if !text {
+        let options = tiled_options(&args);
+
+        match args.format.as_str() {
+            "tmx" => print!("{}", to_tmx(&map, &layout, &options)),
+            "tmj" => println!("{}", to_tmj(&map, &layout, &options)),
+            _ => println!("{}", to_json(&map, &layout, &config)),
+        }
+
+        return ExitCode::from(0);
+    }
 
-    let map = generate(config.unwrap());
     let bytes = map.to_bytes();
-    let width = map.width as i32;
+    let width = map.width as usize;
 
     println!("Map size: {}x{}", map.width, map.height);
-    println!("");
+    println!("Rooms: {}, connections: {}", layout.rooms.len(), layout.connections.len());
+
+    for prefab in layout.prefabs.iter() {
+        println!("Prefab {} in room {}", prefab.name, prefab.room);
+    }
+
+    for repair in layout.repairs.iter() {
+        match repair {
+            Repair::Rerouted { from, to } => println!("Re-routed corridor from room {} to room {}", from, to),
+            Repair::Removed { from, to } => println!("Removed connection from room {} to room {}", from, to),
+            Repair::Added { from, to } => println!("Added connection from room {} to room {}", from, to),
+        }
+    }
+    println!();
     println!("Generated map (ASCII): {}", map.to_ascii());
-    println!("");
+    println!();
     println!("Generated map (bytes):");
 
-    for y in 0..map.height {
-        let y = y as i32;
-        let from = (y * width) as usize;
-        let to = (y * width + width) as usize;
+    for y in 0..map.height as usize {
+        let from = y * width;
+        let to = y * width + width;
 
-        println!("{}", &bytes[from..to].into_iter().map(|i| i.to_string()).collect::<String>());
+        println!("{}", &bytes[from..to].iter().map(|i| i.to_string()).collect::<String>());
     }
 
     ExitCode::from(0)
 }
+
+fn print_stats(stats: &DungeonStats) {
+    let percent = |ratio: f64| format!("{:.1}%", ratio * 100.0);
+    let doors: Vec<String> = stats.doors_per_room.iter().map(|d| d.to_string()).collect();
+
+    println!("Map size: {}x{}", stats.width, stats.height);
+    println!("Rooms: {} placed, {} requested", stats.rooms, stats.requested_rooms);
+    println!("Connections: {}, cycles: {}, repairs: {}", stats.connections, stats.cycles, stats.repairs);
+    println!(
+        "Tiles: floor {}, corridor {}, empty {}, bounding box used {}",
+        percent(stats.floor_ratio), percent(stats.corridor_ratio), percent(stats.empty_ratio), percent(stats.utilisation),
+    );
+    println!("Dead ends: {}, rooms with a single connection: {}", stats.dead_ends, stats.leaf_rooms);
+    println!("Corridor length: average {:.1}, maximum {}", stats.average_corridor_length, stats.max_corridor_length);
+    println!("Connections between rooms: average {:.1}, maximum {}", stats.average_room_distance, stats.max_room_distance);
+    println!("Rooms crossed by corridors: {}", stats.corridor_overlaps);
+    println!("Doors per room: {}", doors.join(" "));
+}
+
+fn tiled_options(args: &Args) -> TiledOptions {
+    let mut options = TiledOptions {
+        tile_width: args.tile_size[0],
+        tile_height: args.tile_size[1],
+        tileset: args.tileset.clone(),
+        ..TiledOptions::default()
+    };
+
+    for (tile, id) in args.tile_ids.iter() {
+        match tile.as_str() {
+            "empty" => options.tile_ids.empty = *id,
+            "floor" => options.tile_ids.floor = *id,
+            "corridor" => options.tile_ids.corridor = *id,
+            "door" => options.tile_ids.door = *id,
+            _ => options.tile_ids.wall = *id,
+        }
+    }
+
+    options
+}
+
+/// Generate the maps of `--seeds` chunk by chunk, and write one JSON line per
+/// seed as soon as its chunk is done. Returns the number of maps generated &
+/// failed.
+fn run_batch(args: &Args, config: &Config, prefabs: &[Prefab], (first, last): (u64, u64)) -> Result<(u64, u64), String> {
+    let options = tiled_options(args);
+    let lines_path = match (args.out_dir.as_ref(), args.jsonl.as_ref()) {
+        (Some(dir), _) => {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+
+            Some(Path::new(dir).join("stats.jsonl"))
+        }
+        (_, Some(path)) => Some(PathBuf::from(path)),
+        _ => None,
+    };
+    let mut lines: Box<dyn Write> = match lines_path.as_ref() {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    let (mut generated, mut failed) = (0, 0);
+
+    for (seed, result) in generate_batch_iter_with_prefabs(config, prefabs, first..=last, BATCH_CHUNK) {
+        let (map, layout) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                failed += 1;
+                // a JSON string, quoted & escaped
+                writeln!(lines, r#"{{"seed":{},"error":{}}}"#, seed, serde_json::Value::from(e.to_string()))
+                    .map_err(|e| format!("Cannot write the stats: {}", e))?;
+
+                continue;
+            }
+        };
+        let config = Config { seed, ..config.clone() };
+        let stats = serde_json::to_string(&DungeonStats::new(&map, &layout, &config)).unwrap();
+
+        generated += 1;
+
+        let line = if let Some(dir) = args.out_dir.as_ref() {
+            let (extension, data) = match args.format.as_str() {
+                "json" => ("json", to_json(&map, &layout, &config)),
+                "tmx" => ("tmx", to_tmx(&map, &layout, &options)),
+                "tmj" => ("tmj", to_tmj(&map, &layout, &options)),
+                _ => ("txt", map.to_ascii().trim_start().to_string()),
+            };
+            let path = Path::new(dir).join(format!("{}.{}", seed, extension));
+            let data = if data.ends_with('\n') { data } else { data + "\n" };
+
+            fs::write(&path, data).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+
+            format!(r#"{{"seed":{},"stats":{}}}"#, seed, stats)
+        } else if args.jsonl.is_some() {
+            format!(r#"{{"seed":{},"stats":{},"dungeon":{}}}"#, seed, stats, to_json_line(&map, &layout, &config))
+        } else {
+            format!(r#"{{"seed":{},"stats":{}}}"#, seed, stats)
+        };
+
+        writeln!(lines, "{}", line).map_err(|e| format!("Cannot write the stats: {}", e))?;
+    }
+
+    lines.flush().map_err(|e| format!("Cannot write the stats: {}", e))?;
+
+    Ok((generated, failed))
+}
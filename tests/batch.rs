@@ -0,0 +1,55 @@
+//! Dungeons of a batch are the same as the ones generated one by one, in the
+//! order of the seeds, whatever the number of threads.
+
+use dungeon_generator::{generate, generate_batch, generate_batch_iter, Config, DungeonStats, Strategy};
+
+#[test]
+fn batch_matches_single_generations() {
+    let config = Config::default();
+    let seeds: Vec<u64> = (1..=40).rev().collect();
+    let batch = generate_batch(&config, &seeds);
+
+    assert_eq!(batch.iter().map(|(seed, _)| *seed).collect::<Vec<_>>(), seeds);
+
+    for (seed, result) in batch {
+        let (map, layout) = result.unwrap();
+        let seeded = Config { seed, ..config.clone() };
+        let (expected_map, expected_layout) = generate(seeded.clone()).unwrap();
+
+        assert_eq!(map.to_ascii(), expected_map.to_ascii(), "Seed {} changed", seed);
+        assert_eq!(DungeonStats::new(&map, &layout, &seeded), DungeonStats::new(&expected_map, &expected_layout, &seeded));
+    }
+}
+
+#[test]
+fn batch_keeps_the_errors() {
+    // too small to hold a single room
+    let config = Config::builder().strategy(Strategy::Bsp).bounds(64, 48).rooms_count(4).build().unwrap();
+    let small = Config { bounds: config.rooms_min_size.clone(), ..config };
+
+    for (_, result) in generate_batch(&small, &[1, 2, 3]) {
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn streamed_batches_match_whole_batches() {
+    let config = Config::default();
+    let seeds: Vec<u64> = (1..=20).rev().collect();
+    let expected: Vec<(u64, String)> = generate_batch(&config, &seeds).into_iter()
+        .map(|(seed, result)| (seed, result.unwrap().0.to_ascii()))
+        .collect();
+
+    for chunk in [0, 1, 7, 64] {
+        let streamed: Vec<(u64, String)> = generate_batch_iter(&config, seeds.iter().copied(), chunk)
+            .map(|(seed, result)| (seed, result.unwrap().0.to_ascii()))
+            .collect();
+
+        assert_eq!(streamed, expected, "Chunks of {}", chunk);
+    }
+
+    // only the chunks which are read are generated
+    let first: Vec<u64> = generate_batch_iter(&config, 0.., 4).take(6).map(|(seed, _)| seed).collect();
+
+    assert_eq!(first, vec![0, 1, 2, 3, 4, 5]);
+}
@@ -0,0 +1,50 @@
+//! Rooms of the bsp strategy stay inside their leaves: they fit in the bounds
+//! and half the minimum spacing is kept free on each side of them.
+
+use dungeon_generator::{generate, Config, MinMax, Rectangle, Strategy, Vector};
+
+fn bsp(seed: u64, spacing: u32) -> Config {
+    Config {
+        seed,
+        rooms_count: 16,
+        rooms_min_size: Vector { x: 4, y: 4 },
+        rooms_max_size: Vector { x: 7, y: 7 },
+        rooms_spacing: MinMax { min: spacing, max: spacing + 2 },
+        path_extension: MinMax { min: 2, max: 4 },
+        strategy: Strategy::Bsp.into(),
+        bounds: Vector { x: 64, y: 48 },
+        ..Config::default()
+    }
+}
+
+/// Free cells between two rooms, along the axis they are apart on
+fn gap(a: &Rectangle, b: &Rectangle) -> i32 {
+    let x = (b.p1.x - a.p2.x).max(a.p1.x - b.p2.x);
+    let y = (b.p1.y - a.p2.y).max(a.p1.y - b.p2.y);
+
+    x.max(y)
+}
+
+#[test]
+fn rooms_stay_inside_their_leaves() {
+    for spacing in [2, 4, 5] {
+        let padding = (spacing / 2) as i32;
+
+        for seed in 0..50 {
+            let (_, layout) = generate(bsp(seed, spacing)).unwrap();
+            let rooms: Vec<&Rectangle> = layout.rooms.iter().map(|r| &r.bounds).collect();
+            let min_x = rooms.iter().map(|r| r.p1.x).min().unwrap();
+            let min_y = rooms.iter().map(|r| r.p1.y).min().unwrap();
+            let max_x = rooms.iter().map(|r| r.p2.x).max().unwrap();
+            let max_y = rooms.iter().map(|r| r.p2.y).max().unwrap();
+
+            assert!(max_x - min_x <= 64 - 2 * padding && max_y - min_y <= 48 - 2 * padding, "Seed {}: rooms out of the bounds", seed);
+
+            for (i, a) in rooms.iter().enumerate() {
+                for (j, b) in rooms.iter().enumerate().skip(i + 1) {
+                    assert!(gap(a, b) >= 2 * padding, "Seed {}: rooms {} and {} are {} cells apart", seed, i, j, gap(a, b));
+                }
+            }
+        }
+    }
+}
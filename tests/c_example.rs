@@ -0,0 +1,59 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Static lib built along with the tests, in target/<profile>/deps next to
+/// the test binary, or copied to target/<profile> by `cargo build`
+fn static_lib() -> PathBuf {
+    let deps = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let built = fs::read_dir(&deps).unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            name.starts_with("libdungeon_generator-") && name.ends_with(".a")
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok());
+
+    match built {
+        Some(entry) => entry.path(),
+        None => deps.parent().unwrap().join("libdungeon_generator.a"),
+    }
+}
+
+/// Compile the C example against the generated header and the static lib,
+/// then run it: any drift between the header and the Rust types makes the
+/// build or the run fail
+#[test]
+fn c_example_builds_and_runs() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let lib = static_lib();
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("dungen");
+    let compiler = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+
+    let status = Command::new(&compiler)
+        .args(["-Wall", "-Wextra", "-Werror", "-std=c11", "-o"])
+        .arg(&program)
+        .arg("-I").arg(root.join("include"))
+        .arg(root.join("examples/c/src/main.c"))
+        .arg(&lib)
+        .args(["-lm", "-lpthread", "-ldl"])
+        .status()
+        .unwrap_or_else(|e| panic!("Cannot run {}: {}", compiler, e));
+
+    assert!(status.success(), "The C example does not compile against include/dungeon_generator.h");
+
+    let output = Command::new(&program).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "The C example failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Map 42"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("Room 0:"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("Stats: 7 rooms placed of 7"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("Farthest room from room 0: room "), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("Generated map (bytes):"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains(" bytes, same tiles"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("\"tiles\": ["), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("\"layout\": {"), "Unexpected output: {}", stdout);
+}
@@ -0,0 +1,54 @@
+//! Caverns of the cave strategy left apart from the biggest one are culled or
+//! joined to it, depending on `cave.pockets`.
+
+use dungeon_generator::{generate, CaveRules, Config, GenerationError, MinMax, Pockets, Strategy, Vector};
+
+fn config(pockets: Pockets, seed: u64) -> Config {
+    Config {
+        seed,
+        rooms_count: 1,
+        rooms_min_size: Vector { x: 4, y: 4 },
+        rooms_max_size: Vector { x: 7, y: 7 },
+        rooms_spacing: MinMax { min: 3, max: 5 },
+        path_extension: MinMax { min: 2, max: 4 },
+        strategy: Strategy::Cave.into(),
+        bounds: Vector { x: 64, y: 48 },
+        cave: CaveRules { pockets: pockets.into(), ..CaveRules::default() },
+        ..Config::default()
+    }
+}
+
+/// Number of tiles of the map with the byte
+fn count(bytes: &[u8], byte: u8) -> usize {
+    bytes.iter().filter(|b| **b == byte).count()
+}
+
+#[test]
+fn culling_keeps_the_biggest_cavern_alone() {
+    for seed in 0..30 {
+        let (culled, culled_layout) = generate(config(Pockets::Cull, seed)).unwrap();
+        let (joined, joined_layout) = generate(config(Pockets::Join, seed)).unwrap();
+        let (culled, joined) = (culled.to_bytes(), joined.to_bytes());
+
+        assert_eq!(culled_layout.rooms.len(), 1, "Seed {}", seed);
+        assert!(culled_layout.connections.is_empty(), "Seed {}", seed);
+        // no corridor
+        assert_eq!(count(&culled, 2), 0, "Seed {}", seed);
+        assert!(count(&culled, 1) >= CaveRules::default().min_pocket as usize, "Seed {}", seed);
+
+        // the same cavern comes first when the others are joined to it
+        assert_eq!(culled_layout.rooms[0].bounds.p1, joined_layout.rooms[0].bounds.p1, "Seed {}", seed);
+        assert_eq!(culled_layout.rooms[0].bounds.p2, joined_layout.rooms[0].bounds.p2, "Seed {}", seed);
+        assert_eq!(joined_layout.connections.len(), joined_layout.rooms.len() - 1, "Seed {}", seed);
+        assert!(count(&culled, 1) <= count(&joined, 1), "Seed {}", seed);
+    }
+}
+
+#[test]
+fn caverns_below_the_min_pocket_are_filled() {
+    let mut config = config(Pockets::Cull, 0);
+
+    config.cave.min_pocket = 64 * 48 + 1;
+
+    assert!(matches!(generate(config), Err(GenerationError::PlacementExhausted { room: 0 })));
+}
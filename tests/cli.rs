@@ -0,0 +1,60 @@
+//! Output of the command line tool
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_dungeon-generator")).args(args).output().unwrap();
+
+    assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stdout));
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn stats_are_printed_alone() {
+    let text = run(&["--seed", "7", "--stats"]);
+
+    assert!(text.starts_with("Map size: "), "{}", text);
+    assert!(!text.contains("Map seed"), "{}", text);
+
+    let json = run(&["--seed", "7", "--stats", "--format", "json"]);
+    let stats: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(stats["requested_rooms"], 11);
+}
+
+#[test]
+fn dumped_configs_give_back_the_same_dungeon() {
+    let flags = ["--seed", "9", "--router", "astar", "--strategy", "bsp", "-r", "14"];
+    let expected = run(&flags);
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+
+    for (format, file) in [("text", "dump.toml"), ("json", "dump.json")] {
+        let path = dir.join(file);
+        let path = path.to_str().unwrap();
+        let dump = run(&[&flags[..], &["--dump-config", "--format", format]].concat());
+
+        fs::write(path, &dump).unwrap();
+
+        assert_eq!(run(&["--config", path]), expected, "{}", file);
+        assert_eq!(run(&["--config", path, "--dump-config", "--format", format]), dump, "{}", file);
+        // the flags override the file
+        assert!(run(&["--config", path, "-r", "5", "--dump-config"]).contains("rooms_count = 5"), "{}", file);
+    }
+}
+
+#[test]
+fn oversized_scales_are_rejected() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("oversized.png");
+    let output = Command::new(env!("CARGO_BIN_EXE_dungeon-generator"))
+        .args(["--render", path.to_str().unwrap(), "--scale", "100000000"])
+        .output()
+        .unwrap();
+
+    // a usage error, not a panic
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--scale"));
+    assert!(!path.exists());
+}
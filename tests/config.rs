@@ -0,0 +1,161 @@
+//! Validation of the config, as done before every generation
+
+use dungeon_generator::{generate, last_error_code, map_create, CaveRules, Config, ConfigError, GenerationError, MinMax, ShapeWeights, Strategy, Vector};
+
+/// Errors of the default config with a few values changed
+fn errors(config: Config) -> Vec<ConfigError> {
+    config.validate().err().unwrap_or_default()
+}
+
+#[test]
+fn the_default_config_is_valid() {
+    assert_eq!(errors(Config::default()), vec![]);
+}
+
+#[test]
+fn zero_rooms_are_rejected() {
+    assert_eq!(errors(Config { rooms_count: 0, ..Config::default() }), vec![ConfigError::Zero { field: "rooms_count" }]);
+    // caves have no rooms to count
+    assert_eq!(errors(Config { rooms_count: 0, strategy: Strategy::Cave.into(), ..Config::default() }), vec![]);
+}
+
+#[test]
+fn min_sizes_above_max_sizes_are_rejected() {
+    let config = Config { rooms_min_size: Vector { x: 9, y: 5 }, rooms_max_size: Vector { x: 7, y: 5 }, ..Config::default() };
+
+    assert_eq!(errors(config), vec![ConfigError::MinAboveMax { field: "rooms_size.x", min: 9, max: 7 }]);
+}
+
+#[test]
+fn rooms_without_space_for_a_door_are_rejected() {
+    let config = Config { rooms_min_size: Vector { x: 4, y: 3 }, ..Config::default() };
+    let errors = errors(config);
+
+    assert_eq!(errors, vec![ConfigError::RoomTooSmall { field: "rooms_min_size.y", value: 3, min: 4 }]);
+    assert!(errors[0].to_string().contains("away from the corners"));
+}
+
+#[test]
+fn sizes_and_spacings_of_any_parity_are_accepted() {
+    for (size, spacing) in [(4, 2), (5, 3), (4, 3), (5, 2)] {
+        let config = Config {
+            rooms_min_size: Vector { x: size, y: size },
+            rooms_spacing: MinMax { min: spacing, max: spacing + 1 },
+            ..Config::default()
+        };
+
+        assert_eq!(errors(config.clone()), vec![]);
+
+        for seed in 0..20 {
+            assert!(generate(Config { seed, ..config.clone() }).is_ok(), "Size {}, spacing {}, seed {}", size, spacing, seed);
+        }
+    }
+}
+
+#[test]
+fn short_ranges_are_rejected() {
+    let config = Config { rooms_spacing: MinMax { min: 1, max: 4 }, path_extension: MinMax { min: 1, max: 4 }, ..Config::default() };
+
+    assert_eq!(errors(config), vec![
+        ConfigError::TooSmall { field: "rooms_spacing.min", value: 1, min: 2 },
+        ConfigError::TooSmall { field: "path_extension.min", value: 1, min: 2 },
+    ]);
+}
+
+#[test]
+fn empty_ranges_are_rejected() {
+    let config = Config { rooms_spacing: MinMax { min: 4, max: 4 }, path_extension: MinMax { min: 5, max: 3 }, ..Config::default() };
+
+    assert_eq!(errors(config), vec![
+        ConfigError::EmptyRange { field: "rooms_spacing", min: 4, max: 4 },
+        ConfigError::EmptyRange { field: "path_extension", min: 5, max: 3 },
+    ]);
+}
+
+#[test]
+fn bounds_too_small_for_bsp_are_rejected() {
+    let config = Config { strategy: Strategy::Bsp.into(), bounds: Vector { x: 7, y: 48 }, ..Config::default() };
+    let min = Config::default().rooms_min_size.x + 4;
+
+    assert_eq!(errors(config.clone()), vec![ConfigError::TooSmall { field: "bounds.x", value: 7, min }]);
+    // the bounds only matter to bsp
+    assert_eq!(errors(Config { strategy: Strategy::Scatter.into(), ..config }), vec![]);
+}
+
+#[test]
+fn zero_shape_weights_are_rejected() {
+    let shapes = ShapeWeights { rectangle: 0, ellipse: 0, cross: 0, l_shape: 0, pillars: 0, composite: 0 };
+
+    assert_eq!(errors(Config { shapes, ..Config::default() }), vec![ConfigError::Zero { field: "shapes" }]);
+}
+
+#[test]
+fn cave_rules_out_of_range_are_rejected() {
+    let cave = CaveRules { fill: 101, birth: 9, survival: 9, ..CaveRules::default() };
+    let config = Config { strategy: Strategy::Cave.into(), cave, ..Config::default() };
+
+    assert_eq!(errors(config), vec![
+        ConfigError::TooBig { field: "cave.fill", value: 101, max: 100 },
+        ConfigError::TooBig { field: "cave.birth", value: 9, max: 8 },
+        ConfigError::TooBig { field: "cave.survival", value: 9, max: 8 },
+    ]);
+}
+
+#[test]
+fn loop_factors_above_100_are_rejected() {
+    let config = Config { loop_factor: 101, ..Config::default() };
+
+    assert_eq!(errors(config), vec![ConfigError::TooBig { field: "loop_factor", value: 101, max: 100 }]);
+}
+
+#[test]
+fn unknown_walls_are_rejected() {
+    let config = Config { walls: 5, ..Config::default() };
+
+    assert_eq!(errors(config.clone()), vec![ConfigError::UnknownCode { field: "walls", value: 5 }]);
+    assert!(matches!(generate(config), Err(GenerationError::InvalidConfig(_))));
+}
+
+#[test]
+fn unknown_router_is_rejected() {
+    let config = Config { router: 2, ..Config::default() };
+
+    assert_eq!(errors(config.clone()), vec![ConfigError::UnknownCode { field: "router", value: 2 }]);
+    assert!(matches!(generate(config), Err(GenerationError::InvalidConfig(_))));
+}
+
+#[test]
+fn unknown_strategy_is_rejected() {
+    let config = Config { strategy: 3, ..Config::default() };
+
+    assert_eq!(errors(config.clone()), vec![ConfigError::UnknownCode { field: "strategy", value: 3 }]);
+    assert!(matches!(generate(config), Err(GenerationError::InvalidConfig(_))));
+}
+
+#[test]
+fn unknown_pockets_are_rejected() {
+    let config = Config { cave: CaveRules { pockets: 2, ..CaveRules::default() }, ..Config::default() };
+
+    assert_eq!(errors(config.clone()), vec![ConfigError::UnknownCode { field: "cave.pockets", value: 2 }]);
+    assert!(matches!(generate(config), Err(GenerationError::InvalidConfig(_))));
+}
+
+#[test]
+fn unknown_codes_are_rejected_by_map_create() {
+    let config = Box::into_raw(Box::new(Config { walls: 5, ..Config::default() }));
+    let handle = unsafe { map_create(config) };
+
+    assert!(handle.is_null());
+    assert_eq!(last_error_code(), GenerationError::InvalidConfig(String::new()).code());
+}
+
+#[test]
+fn codes_are_written_by_name() {
+    let config = Config::from_toml("walls = \"four\"").unwrap();
+
+    assert_eq!(config.walls, 4);
+    assert!(config.to_toml().unwrap().contains("walls = \"four\""));
+    assert!(Config::from_toml("walls = \"six\"").is_err());
+    // unknown codes are kept as numbers, to show them
+    assert!(Config { walls: 5, ..Config::default() }.to_json().contains("\"walls\": 5"));
+}
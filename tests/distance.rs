@@ -0,0 +1,56 @@
+//! Walking distances over the generated maps: every walkable tile is reached
+//! from any room, the walls and the empty tiles never are.
+
+use dungeon_generator::{generate, Config, Strategy, TileCosts, Vector};
+
+#[test]
+fn every_walkable_tile_is_reached() {
+    for strategy in [Strategy::Scatter, Strategy::Bsp, Strategy::Cave] {
+        let config = Config::builder().strategy(strategy).build().unwrap();
+        let (map, layout) = generate(config).unwrap();
+        let sources = map.room_tiles(&layout.rooms[0]);
+        let distances = map.distance_map(&sources);
+
+        assert!(!sources.is_empty());
+
+        for (byte, distance) in map.to_bytes().into_iter().zip(distances.distances()) {
+            // floor, corridor & door
+            assert_eq!(matches!(byte, 1..=3), distance.is_some(), "{:?}: tile {} reached: {:?}", strategy, byte, distance);
+        }
+
+        for source in sources.iter() {
+            assert_eq!(distances.get(source), Some(0));
+        }
+    }
+}
+
+#[test]
+fn costs_scale_the_distances() {
+    let (map, layout) = generate(Config::default()).unwrap();
+    let start = [layout.connections[0].from_door.clone()];
+    let unit = map.distance_map(&start);
+    let double = map.distance_map_with_costs(&start, &TileCosts { floor: 2, corridor: 2, door: 2 });
+
+    for (a, b) in unit.distances().iter().zip(double.distances()) {
+        assert_eq!(a.map(|d| d * 2), *b);
+    }
+
+    // a step to a walkable neighbour costs one tile
+    let door = &start[0];
+    let steps = [(0, -1), (1, 0), (0, 1), (-1, 0)].map(|(dx, dy)| unit.get(&Vector { x: door.x + dx, y: door.y + dy }));
+
+    assert!(steps.iter().flatten().all(|d| *d == 1));
+    assert_eq!(unit.get(&Vector { x: -1, y: 0 }), None);
+}
+
+#[test]
+fn farthest_room_is_the_most_distant() {
+    let (map, layout) = generate(Config::default()).unwrap();
+    let (room, distance) = map.farthest_room(&layout, 0).unwrap();
+    let distances = map.distance_map(&map.room_tiles(&layout.rooms[0])).room_distances(&layout);
+
+    assert_ne!(room, 0);
+    assert_eq!(distances[room], Some(distance));
+    assert!(distances.iter().flatten().all(|d| *d <= distance));
+    assert_eq!(map.farthest_room(&layout, layout.rooms.len()), None);
+}
@@ -0,0 +1,49 @@
+//! Errors stopping a generation, as returned by `generate` and reported by
+//! the C API
+
+use dungeon_generator::{generate, generate_with_router, last_error_code, last_error_message, map_create, map_destroy, CaveRules, Config, CorridorRouter, GenerationError, Route, Strategy, Vector};
+use rand_chacha::ChaCha8Rng;
+use std::ffi::CStr;
+
+/// Router finding no corridor at all
+struct Blocked;
+
+impl CorridorRouter for Blocked {
+    fn route(&mut self, _: &Route, _: &mut ChaCha8Rng) -> Option<Vec<Vector<i32>>> {
+        None
+    }
+}
+
+#[test]
+fn each_error_is_returned_by_generate() {
+    let invalid = Config { rooms_count: 0, ..Config::default() };
+    // no pocket of the cave is big enough to keep
+    let cave = Config { strategy: Strategy::Cave.into(), cave: CaveRules { min_pocket: u32::MAX, ..CaveRules::default() }, ..Config::default() };
+    // sizes above `i32::MAX` have no coordinates
+    let huge = Config { rooms_min_size: Vector { x: 1 << 31, y: 5 }, rooms_max_size: Vector { x: 1 << 31, y: 5 }, ..Config::default() };
+
+    assert!(matches!(generate(invalid), Err(GenerationError::InvalidConfig(_))));
+    assert!(matches!(generate(cave), Err(GenerationError::PlacementExhausted { room: 0 })));
+    assert!(matches!(generate_with_router(Config::default(), Box::new(Blocked)), Err(GenerationError::PathUnsolvable { .. })));
+    assert!(matches!(generate(huge), Err(GenerationError::CoordinateOverflow)));
+}
+
+#[test]
+fn invalid_configs_make_map_create_fail() {
+    let config = Box::into_raw(Box::new(Config { rooms_count: 0, ..Config::default() }));
+    let handle = unsafe { map_create(config) };
+    let message = last_error_message();
+
+    assert!(handle.is_null());
+    assert_eq!(last_error_code(), GenerationError::InvalidConfig(String::new()).code());
+    assert!(!message.is_null());
+    assert!(unsafe { CStr::from_ptr(message) }.to_str().unwrap().contains("rooms_count cannot be zero"));
+
+    // the error is cleared by the next success
+    let handle = unsafe { map_create(Box::into_raw(Box::new(Config::default()))) };
+
+    assert!(!handle.is_null());
+    assert_eq!(last_error_code(), 0);
+    assert!(last_error_message().is_null());
+    assert_eq!(unsafe { map_destroy(handle) }, 0);
+}
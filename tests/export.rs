@@ -0,0 +1,169 @@
+//! Exports of the generated dungeons, read back to check they describe the map
+//! and the layout they were written from.
+
+use dungeon_generator::{generate, render, to_json, to_json_line, to_svg, to_tmj, to_tmx, Config, ImageTooBig, MinMax, Palette, RenderOptions, SvgOptions, TileIds, TiledOptions, Vector, MAX_PIXELS};
+use serde_json::Value;
+
+fn config(seed: u64) -> Config {
+    Config {
+        seed,
+        rooms_count: 20,
+        rooms_min_size: Vector { x: 4, y: 4 },
+        rooms_max_size: Vector { x: 7, y: 7 },
+        rooms_spacing: MinMax { min: 3, max: 5 },
+        path_extension: MinMax { min: 2, max: 4 },
+        ..Config::default()
+    }
+}
+
+/// Value of an attribute of an XML element
+fn attribute(element: &str, name: &str) -> i64 {
+    let start = element.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+    let end = start + element[start..].find('"').unwrap();
+
+    element[start..end].parse().unwrap()
+}
+
+#[test]
+fn json_holds_the_map_the_layout_and_the_config() {
+    let config = config(12);
+    let (map, layout) = generate(config.clone()).unwrap();
+    let (width, height) = map.size();
+    let document: Value = serde_json::from_str(&to_json(&map, &layout, &config)).unwrap();
+    let rows: Vec<&str> = document["map"]["tiles"].as_array().unwrap().iter().map(|row| row.as_str().unwrap()).collect();
+
+    assert_eq!(document["map"]["width"], width);
+    assert_eq!(document["map"]["height"], height);
+    assert_eq!(rows, map.to_ascii().lines().filter(|row| !row.is_empty()).collect::<Vec<_>>());
+    assert!(rows.iter().all(|row| row.len() == width as usize));
+    assert_eq!(document["map"]["legend"].as_array().unwrap().len(), 5);
+
+    assert_eq!(document["layout"]["rooms"].as_array().unwrap().len(), layout.rooms.len());
+    assert_eq!(document["layout"]["connections"].as_array().unwrap().len(), layout.connections.len());
+    assert_eq!(document["layout"]["rooms"][0]["bounds"]["p1"]["x"], layout.rooms[0].bounds.p1.x);
+
+    // the config is enough to generate the dungeon again
+    let config: Config = serde_json::from_value(document["config"].clone()).unwrap();
+
+    assert_eq!(config.seed, 12);
+    assert_eq!(generate(config).unwrap().0.to_bytes(), map.to_bytes());
+}
+
+#[test]
+fn json_lines_hold_the_same_document() {
+    let config = config(0);
+    let (map, layout) = generate(config.clone()).unwrap();
+    let line = to_json_line(&map, &layout, &config);
+
+    assert!(!line.contains('\n'));
+    assert_eq!(serde_json::from_str::<Value>(&line).unwrap(), serde_json::from_str::<Value>(&to_json(&map, &layout, &config)).unwrap());
+}
+
+#[test]
+fn tiled_maps_hold_the_tiles_and_the_layout() {
+    let (map, layout) = generate(config(0)).unwrap();
+    let options = TiledOptions {
+        tile_ids: TileIds { empty: 0, floor: 5, corridor: 6, door: 7, wall: 9 },
+        tile_width: 8,
+        tile_height: 12,
+        tileset: Some(String::from("dungeon.tsx")),
+    };
+    let ids = [0, 5, 6, 7, 9];
+    let tiles: Vec<u64> = map.to_bytes().into_iter().map(|byte| ids[byte as usize]).collect();
+    let tmj: Value = serde_json::from_str(&to_tmj(&map, &layout, &options)).unwrap();
+    let objects = tmj["layers"][1]["objects"].as_array().unwrap();
+    let rooms: Vec<&Value> = objects.iter().filter(|o| o["type"] == "room").collect();
+
+    assert_eq!(tmj["width"], map.size().0);
+    assert_eq!(tmj["tilesets"][0]["source"], "dungeon.tsx");
+    assert_eq!(tmj["layers"][0]["data"].as_array().unwrap().iter().map(|id| id.as_u64().unwrap()).collect::<Vec<_>>(), tiles);
+    assert_eq!(rooms.len(), layout.rooms.len());
+    assert_eq!(rooms[0]["x"], layout.rooms[0].bounds.p1.x * 8);
+    assert_eq!(rooms[0]["y"], layout.rooms[0].bounds.p1.y * 12);
+    // each door is at the center of its tile
+    assert!(objects.iter().filter(|o| o["type"] == "door").all(|o| o["point"] == true && o["x"].as_u64().unwrap() % 8 == 4));
+
+    // same tiles & objects in the XML map
+    let tmx = to_tmx(&map, &layout, &options);
+    let csv = tmx.split(r#"<data encoding="csv">"#).nth(1).unwrap().split("</data>").next().unwrap();
+    let csv: Vec<u64> = csv.split(',').map(|id| id.trim().parse().unwrap()).collect();
+
+    assert_eq!(csv, tiles);
+    assert_eq!(tmx.matches("<object ").count(), objects.len());
+    assert_eq!(tmx.matches("<point/>").count(), objects.len() - rooms.len());
+    assert!(tmx.contains(r#"<tileset firstgid="1" source="dungeon.tsx"/>"#));
+}
+
+#[test]
+fn images_draw_one_square_per_tile() {
+    let (map, layout) = generate(config(0)).unwrap();
+    let palette = Palette::default();
+    let image = render(&map, &layout, &RenderOptions { scale: 3, ..RenderOptions::default() }).unwrap();
+    let colors = [palette.empty, palette.floor, palette.corridor, palette.door, palette.wall];
+    let (width, height) = map.size();
+
+    assert_eq!((image.width, image.height), (width * 3, height * 3));
+
+    for (n, byte) in map.to_bytes().into_iter().enumerate() {
+        let (x, y) = (n % width as usize, n / width as usize);
+
+        for (px, py) in [(x * 3, y * 3), (x * 3 + 2, y * 3 + 2)] {
+            let i = (px + py * image.width as usize) * 3;
+
+            assert_eq!(image.pixels[i..i + 3], colors[byte as usize], "Tile ({}, {})", x, y);
+        }
+    }
+
+    let ppm = image.to_ppm();
+    let header = format!("P6\n{} {}\n255\n", image.width, image.height);
+
+    assert_eq!(ppm[..header.len()], *header.as_bytes());
+    assert_eq!(ppm[header.len()..], image.pixels[..]);
+
+    let png = image.to_png();
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+
+    assert_eq!((info.width, info.height, info.color_type), (image.width, image.height, png::ColorType::Rgb));
+    assert_eq!(pixels, image.pixels);
+}
+
+#[test]
+fn images_bigger_than_the_limit_are_refused() {
+    let (map, layout) = generate(config(0)).unwrap();
+    let (width, height) = map.size();
+    let error = render(&map, &layout, &RenderOptions { scale: u32::MAX, ..RenderOptions::default() }).err().unwrap();
+
+    assert_eq!(error, ImageTooBig { width: width as u64 * u32::MAX as u64, height: height as u64 * u32::MAX as u64 });
+
+    // the biggest scale of the map below the limit
+    let scale = ((MAX_PIXELS / (width as u64 * height as u64)) as f64).sqrt() as u32;
+
+    assert!(render(&map, &layout, &RenderOptions { scale: scale + 1, ..RenderOptions::default() }).is_err());
+    assert!((width as u64 * scale as u64) * (height as u64 * scale as u64) <= MAX_PIXELS);
+}
+
+#[test]
+fn svg_draws_the_tiles_the_layout_and_the_graph() {
+    let (map, layout) = generate(config(0)).unwrap();
+    let svg = to_svg(&map, &layout, &SvgOptions { scale: 4, graph: true, ..SvgOptions::default() });
+    let lines: Vec<&str> = svg.lines().collect();
+    let tiles = lines.iter()
+        .skip_while(|line| !line.contains(r#"<g class="tiles">"#))
+        .skip(1)
+        .take_while(|line| !line.starts_with("</g>"));
+    let area: i64 = tiles.map(|rect| attribute(rect, "width") * attribute(rect, "height")).sum();
+    let count = |prefix: &str| lines.iter().filter(|line| line.starts_with(prefix)).count();
+
+    assert_eq!(attribute(lines[0], "width"), map.size().0 as i64 * 4);
+    assert_eq!(area, map.to_bytes().iter().filter(|byte| **byte != 0).count() as i64 * 16);
+    assert_eq!(count(r#"<rect class="room""#), layout.rooms.len());
+    assert_eq!(count(r#"<polyline class="path""#), layout.connections.len());
+    assert_eq!(count(r#"<circle class="door""#), layout.connections.len() * 2);
+    assert_eq!(count(r#"<line class="graph""#), layout.connections.len());
+    assert_eq!(count(r#"<circle class="graph""#), layout.rooms.len());
+
+    // no graph unless asked for
+    assert!(!to_svg(&map, &layout, &SvgOptions::default()).contains(r#"class="graph""#));
+}
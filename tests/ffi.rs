@@ -0,0 +1,147 @@
+//! The C API, called the way the C example does
+
+use dungeon_generator::{map_as_bytes, map_bytes_free, map_connection, map_connection_waypoint, map_connections_count, map_copy_bytes, map_create, map_destroy, map_room, map_rooms_count, map_size, Config, Handle, RoomShape, Vector};
+use std::ptr;
+
+const FLOOR: u8 = 1;
+const CORRIDOR: u8 = 2;
+const DOOR: u8 = 3;
+const WALKABLE: [u8; 3] = [FLOOR, CORRIDOR, DOOR];
+
+fn handle(seed: u64) -> *mut Handle {
+    let handle = unsafe { map_create(Box::into_raw(Box::new(Config { seed, ..Config::default() }))) };
+
+    assert!(!handle.is_null());
+
+    handle
+}
+
+#[test]
+fn bytes_are_returned_then_released() {
+    let handle = handle(1);
+
+    unsafe {
+        let size = map_size(handle);
+        let mut length = 0;
+        let bytes = map_as_bytes(handle, &mut length);
+
+        assert!(!bytes.is_null());
+        assert_eq!(length, (size.x * size.y) as usize);
+
+        let mut copy = vec![0; length];
+
+        assert_eq!(map_copy_bytes(handle, copy.as_mut_ptr(), copy.len()), length);
+        assert_eq!(std::slice::from_raw_parts(bytes, length), copy.as_slice());
+
+        map_bytes_free(bytes, length);
+        map_bytes_free(ptr::null_mut(), 0);
+
+        // nothing to return without a map
+        let mut length = 1;
+
+        assert!(map_as_bytes(ptr::null_mut(), &mut length).is_null());
+        assert_eq!(length, 0);
+
+        map_destroy(handle);
+    }
+}
+
+#[test]
+fn short_buffers_are_left_untouched() {
+    let handle = handle(2);
+
+    unsafe {
+        let needed = map_copy_bytes(handle, ptr::null_mut(), 0);
+        let mut short = vec![0xff; needed - 1];
+
+        assert!(needed > 0);
+        assert_eq!(map_copy_bytes(handle, short.as_mut_ptr(), short.len()), needed);
+        assert!(short.iter().all(|byte| *byte == 0xff));
+        assert_eq!(map_copy_bytes(ptr::null_mut(), short.as_mut_ptr(), short.len()), 0);
+
+        map_destroy(handle);
+    }
+}
+
+#[test]
+fn layouts_out_of_range_are_empty() {
+    let handle = handle(3);
+
+    unsafe {
+        let rooms = map_rooms_count(handle);
+        let connections = map_connections_count(handle);
+        let waypoints = map_connection(handle, 0).waypoints_count;
+
+        assert!(rooms > 0 && connections > 0);
+        assert_eq!(map_room(handle, rooms).id, usize::MAX);
+        assert_eq!(map_connection(handle, connections).id, usize::MAX);
+        assert_eq!(map_connection(handle, connections).waypoints_count, 0);
+        assert_eq!(map_connection_waypoint(handle, connections, 0), Vector { x: 0, y: 0 });
+        assert_eq!(map_connection_waypoint(handle, 0, waypoints), Vector { x: 0, y: 0 });
+
+        assert_eq!(map_rooms_count(ptr::null_mut()), 0);
+        assert_eq!(map_room(ptr::null_mut(), 0).id, usize::MAX);
+        assert_eq!(map_connections_count(ptr::null_mut()), 0);
+        assert_eq!(map_connection(ptr::null_mut(), 0).id, usize::MAX);
+        assert_eq!(map_connection_waypoint(ptr::null_mut(), 0, 0), Vector { x: 0, y: 0 });
+
+        map_destroy(handle);
+    }
+}
+
+#[test]
+fn layouts_match_the_tiles() {
+    for seed in 0..20 {
+        let handle = handle(seed);
+
+        unsafe {
+            let size = map_size(handle);
+            let mut bytes = vec![0; (size.x * size.y) as usize];
+
+            map_copy_bytes(handle, bytes.as_mut_ptr(), bytes.len());
+
+            let tile = |p: &Vector<i32>| bytes[(p.x + p.y * size.x as i32) as usize];
+
+            for index in 0..map_rooms_count(handle) {
+                let room = map_room(handle, index);
+                let (p1, p2) = (&room.bounds.p1, &room.bounds.p2);
+                let inside: Vec<u8> = (p1.y..p2.y).flat_map(|y| (p1.x..p2.x).map(move |x| Vector { x, y })).map(|p| tile(&p)).collect();
+
+                assert_eq!(room.id, index);
+                assert!(inside.contains(&FLOOR), "Seed {}: room {} has no floor", seed, index);
+
+                // corridors crossing a room leave doors on its floor
+                if room.shape == RoomShape::Rectangle {
+                    assert!(inside.iter().all(|t| *t == FLOOR || *t == DOOR), "Seed {}: room {} is not all floor", seed, index);
+                }
+            }
+
+            for index in 0..map_connections_count(handle) {
+                let connection = map_connection(handle, index);
+                let count = connection.waypoints_count;
+                let waypoints: Vec<Vector<i32>> = (0..count).map(|w| map_connection_waypoint(handle, index, w)).collect();
+
+                assert!(count >= 2);
+                assert_eq!(waypoints[0], connection.from_door);
+                assert_eq!(waypoints[count - 1], connection.to_door);
+                assert_eq!(tile(&connection.from_door), DOOR, "Seed {}: connection {}", seed, index);
+                assert_eq!(tile(&connection.to_door), DOOR, "Seed {}: connection {}", seed, index);
+
+                // corridors run straight between the waypoints
+                for pair in waypoints.windows(2) {
+                    let (a, b) = (&pair[0], &pair[1]);
+
+                    assert!(a.x == b.x || a.y == b.y, "Seed {}: connection {} turns between {:?} and {:?}", seed, index, a, b);
+
+                    for y in a.y.min(b.y)..=a.y.max(b.y) {
+                        for x in a.x.min(b.x)..=a.x.max(b.x) {
+                            assert!(WALKABLE.contains(&tile(&Vector { x, y })), "Seed {}: connection {} is blocked at ({}, {})", seed, index, x, y);
+                        }
+                    }
+                }
+            }
+
+            map_destroy(handle);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Prefabs of `examples/prefabs` mixed with the procedural rooms
+
+use dungeon_generator::{generate_with_prefabs, Config, GenerationError, Prefab, Strategy};
+use std::path::PathBuf;
+
+fn example_prefabs() -> Vec<Prefab> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/prefabs");
+
+    ["arena.txt", "shrine.txt", "vault.txt"].iter().map(|name| Prefab::load(dir.join(name)).unwrap()).collect()
+}
+
+#[test]
+fn bsp_places_the_required_prefabs_on_every_seed() {
+    let prefabs = example_prefabs();
+    let config = Config::builder().strategy(Strategy::Bsp).build().unwrap();
+
+    for seed in 0..100 {
+        let (_, layout) = match generate_with_prefabs(Config { seed, ..config.clone() }, &prefabs) {
+            Ok(result) => result,
+            Err(error) => panic!("Seed {}: {}", seed, error),
+        };
+
+        assert!(layout.prefabs.iter().any(|p| p.name == "boss arena"), "Seed {}: no boss arena", seed);
+    }
+}
+
+#[test]
+fn bsp_rejects_bounds_too_small_for_a_required_prefab() {
+    let prefabs = example_prefabs();
+    // the boss arena is 17 tiles wide
+    let config = Config::builder().strategy(Strategy::Bsp).bounds(16, 40).build().unwrap();
+
+    for seed in 0..10 {
+        let result = generate_with_prefabs(Config { seed, ..config.clone() }, &prefabs);
+
+        assert!(matches!(result, Err(GenerationError::InvalidConfig(_))), "Seed {}", seed);
+    }
+}
+
+#[test]
+fn dense_bsp_layouts_with_prefabs_are_connected() {
+    let prefabs = example_prefabs();
+    let config = Config::builder().strategy(Strategy::Bsp).rooms_count(20).build().unwrap();
+
+    for seed in 0..200 {
+        if let Err(error) = generate_with_prefabs(Config { seed, ..config.clone() }, &prefabs) {
+            panic!("Seed {}: {}", seed, error);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Example output of each preset with its default seed, kept in
+//! `tests/presets/<name>.txt`. A change of these files means a change of the
+//! dungeons generated from the presets.
+
+use dungeon_generator::{generate, Config, GenerationError, PRESETS};
+
+fn check(name: &str, expected: &str) {
+    let config = Config::preset(name).unwrap();
+
+    assert_eq!(config.validate(), Ok(()), "Preset {} is not valid", name);
+
+    let (map, layout) = generate(config).unwrap();
+
+    assert!(layout.repairs.is_empty(), "Preset {} needed repairs: {:?}", name, layout.repairs);
+    assert_eq!(map.to_ascii().trim(), expected.trim(), "Preset {} changed", name);
+}
+
+#[test]
+fn tight_crypt() {
+    check("tight-crypt", include_str!("presets/tight-crypt.txt"));
+}
+
+#[test]
+fn sprawling_caves() {
+    check("sprawling-caves", include_str!("presets/sprawling-caves.txt"));
+}
+
+#[test]
+fn arena() {
+    check("arena", include_str!("presets/arena.txt"));
+}
+
+#[test]
+fn labyrinth() {
+    check("labyrinth", include_str!("presets/labyrinth.txt"));
+}
+
+#[test]
+fn every_preset_is_listed() {
+    for name in PRESETS {
+        assert!(Config::preset(name).is_ok(), "Preset {} is listed but unknown", name);
+    }
+}
+
+#[test]
+fn unknown_preset() {
+    assert!(matches!(Config::preset("dungeon"), Err(GenerationError::InvalidConfig(_))));
+}
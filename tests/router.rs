@@ -0,0 +1,57 @@
+//! Corridors traced by the A* router
+
+use dungeon_generator::{generate, Config, ConfigError, Router, Vector};
+
+#[test]
+fn astar_succeeds_at_the_smallest_spacing() {
+    let config = Config::builder()
+        .rooms_count(30)
+        .rooms_min_size(4, 4)
+        .rooms_max_size(4, 4)
+        .rooms_spacing(3, 4)
+        .path_extension(2, 3)
+        .router(Router::AStar)
+        .build()
+        .unwrap();
+
+    for seed in 0..100 {
+        if let Err(error) = generate(Config { seed, ..config.clone() }) {
+            panic!("Seed {}: {}", seed, error);
+        }
+    }
+}
+
+#[test]
+fn astar_needs_an_even_cell_between_the_rooms() {
+    let errors = Config::builder().rooms_spacing(2, 3).router(Router::AStar).build().unwrap_err();
+
+    assert_eq!(errors, vec![ConfigError::TooSmall { field: "rooms_spacing.min", value: 2, min: 3 }]);
+    assert!(Config::builder().rooms_spacing(2, 3).build().is_ok());
+}
+
+#[test]
+fn astar_corridors_stay_out_of_the_rooms() {
+    let config = Config::builder().rooms_count(20).router(Router::AStar).build().unwrap();
+
+    for seed in 0..50 {
+        let (_, layout) = generate(Config { seed, ..config.clone() }).unwrap();
+
+        for connection in layout.connections.iter() {
+            let waypoints = &connection.waypoints;
+            let cells: Vec<Vector<i32>> = waypoints.windows(2)
+                .flat_map(|w| {
+                    let (a, b) = (&w[0], &w[1]);
+
+                    (a.y.min(b.y)..=a.y.max(b.y)).flat_map(move |y| (a.x.min(b.x)..=a.x.max(b.x)).map(move |x| Vector { x, y }))
+                })
+                .filter(|p| *p != connection.from_door && *p != connection.to_door)
+                .collect();
+
+            for room in layout.rooms.iter() {
+                let inside = cells.iter().find(|p| room.bounds.is_inside((*p).clone()));
+
+                assert!(inside.is_none(), "Seed {}: connection {} goes through room {} at {:?}", seed, connection.id, room.id, inside);
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Queries of the spatial index give the same rooms as a scan of every room.
+
+use dungeon_generator::{Rectangle, SpatialIndex, Vector};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+fn random_rect(rng: &mut ChaCha8Rng, max_size: i32) -> Rectangle {
+    let p1 = Vector { x: rng.gen_range(-100..100), y: rng.gen_range(-100..100) };
+    let p2 = Vector { x: p1.x + rng.gen_range(1..=max_size), y: p1.y + rng.gen_range(1..=max_size) };
+
+    Rectangle { p1, p2 }
+}
+
+#[test]
+fn queries_match_a_scan_of_every_room() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+    for cell in [1, 5, 16, 64] {
+        let mut index = SpatialIndex::new(cell);
+        let rects: Vec<Rectangle> = (0..200).map(|_| random_rect(&mut rng, 20)).collect();
+
+        for rect in rects.iter() {
+            index.insert(rect.clone());
+        }
+
+        assert_eq!(index.len(), rects.len());
+        assert_eq!(index.bounds().unwrap().p1.x, rects.iter().map(|r| r.p1.x).min().unwrap());
+        assert_eq!(index.bounds().unwrap().p2.y, rects.iter().map(|r| r.p2.y).max().unwrap());
+
+        for _ in 0..500 {
+            let area = random_rect(&mut rng, 40);
+            let expected: Vec<usize> = (0..rects.len()).filter(|i| rects[*i].overlap(&area)).collect();
+
+            assert_eq!(index.query(&area), expected, "Cell {}, area {:?}", cell, area);
+
+            // a corner of the area, and the last cell of a room
+            let room = &rects[rng.gen_range(0..rects.len())];
+
+            for p in [area.p1, Vector { x: room.p2.x - 1, y: room.p2.y - 1 }] {
+                assert_eq!(index.contains(&p), rects.iter().any(|r| r.is_inside(p.clone())), "Cell {}, point {:?}", cell, p);
+            }
+        }
+    }
+}
+
+#[test]
+fn an_empty_index_holds_nothing() {
+    let index = SpatialIndex::default();
+    let area = Rectangle { p1: Vector { x: -10, y: -10 }, p2: Vector { x: 10, y: 10 } };
+
+    assert!(index.is_empty());
+    assert!(index.bounds().is_none());
+    assert!(index.query(&area).is_empty());
+    assert!(!index.contains(&Vector { x: 0, y: 0 }));
+}
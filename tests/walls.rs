@@ -0,0 +1,45 @@
+//! Corridors are kept apart from the floor of the rooms by their walls, and only
+//! open on a room through a door.
+
+use dungeon_generator::{generate, Config, Router, ShapeWeights, Strategy};
+
+const FLOOR: u8 = 1;
+const CORRIDOR: u8 = 2;
+
+fn check(name: &str, config: Config) {
+    for seed in 0..60 {
+        let (map, _) = generate(Config { seed, ..config.clone() }).unwrap();
+        let (width, height) = map.size();
+        let (width, height) = (width as usize, height as usize);
+        let bytes = map.to_bytes();
+
+        for (n, byte) in bytes.iter().enumerate() {
+            if *byte != CORRIDOR {
+                continue;
+            }
+
+            let (x, y) = (n % width, n / width);
+            let neighbours = [
+                (x > 0).then(|| n - 1),
+                (x + 1 < width).then(|| n + 1),
+                (y > 0).then(|| n - width),
+                (y + 1 < height).then(|| n + width),
+            ];
+
+            assert!(
+                neighbours.into_iter().flatten().all(|m| bytes[m] != FLOOR),
+                "{}, seed {}: corridor at ({}, {}) opens on a floor", name, seed, x, y,
+            );
+        }
+    }
+}
+
+#[test]
+fn corridors_only_open_on_the_rooms_through_doors() {
+    let shapes = ShapeWeights { rectangle: 1, ellipse: 1, cross: 1, l_shape: 1, pillars: 1, composite: 1 };
+
+    check("rotation", Config::default());
+    check("shapes", Config::builder().shapes(shapes).build().unwrap());
+    check("bsp", Config::builder().strategy(Strategy::Bsp).build().unwrap());
+    check("astar", Config::builder().router(Router::AStar).shapes(shapes).build().unwrap());
+}